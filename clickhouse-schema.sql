@@ -8,7 +8,10 @@ CREATE TABLE IF NOT EXISTS order_fills (
     id String,
     transaction_hash FixedString(66),
     block_number UInt64 Codec(Delta, ZSTD),
+    block_hash String,
     block_timestamp DateTime Codec(DoubleDelta, ZSTD),
+    log_index UInt32,
+    ordinal UInt64,
     order_hash FixedString(66),
     maker LowCardinality(String),
     taker LowCardinality(String),
@@ -22,10 +25,21 @@ CREATE TABLE IF NOT EXISTS order_fills (
 
     -- V2 additions
     exchange_version LowCardinality(String) DEFAULT 'v1',
+    exchange LowCardinality(String),
     token_id String,
     side_raw UInt8,
     builder String,
     metadata String,
+    metadata_layout LowCardinality(String),
+    metadata_value String,
+
+    -- Transaction context
+    tx_from LowCardinality(String),
+    tx_to LowCardinality(String),
+    tx_index UInt32,
+    gas_used UInt64,
+    effective_gas_price UInt256,
+    call_address LowCardinality(String),
 
     -- Materialized columns for analytics
     date Date MATERIALIZED toDate(block_timestamp),
@@ -36,8 +50,44 @@ PARTITION BY toYYYYMM(date)
 ORDER BY (block_timestamp, maker_asset_id, id)
 SETTINGS index_granularity = 8192;
 
+-- Columns added since v0.3.1, for an order_fills table created by an earlier schema
+ALTER TABLE order_fills
+    ADD COLUMN IF NOT EXISTS block_hash String,
+    ADD COLUMN IF NOT EXISTS log_index UInt32,
+    ADD COLUMN IF NOT EXISTS ordinal UInt64,
+    ADD COLUMN IF NOT EXISTS exchange LowCardinality(String),
+    ADD COLUMN IF NOT EXISTS metadata_layout LowCardinality(String),
+    ADD COLUMN IF NOT EXISTS metadata_value String,
+    ADD COLUMN IF NOT EXISTS tx_from LowCardinality(String),
+    ADD COLUMN IF NOT EXISTS tx_to LowCardinality(String),
+    ADD COLUMN IF NOT EXISTS tx_index UInt32,
+    ADD COLUMN IF NOT EXISTS gas_used UInt64,
+    ADD COLUMN IF NOT EXISTS effective_gas_price UInt256,
+    ADD COLUMN IF NOT EXISTS call_address LowCardinality(String);
+
+-- Consistency Violations Table
+-- Fill/match invariants that failed, from map_consistency_checks
+CREATE TABLE IF NOT EXISTS consistency_violations (
+    id String,
+    transaction_hash FixedString(66),
+    block_number UInt64 Codec(Delta, ZSTD),
+    block_hash String,
+    block_timestamp DateTime Codec(DoubleDelta, ZSTD),
+    ordinal UInt64,
+    exchange LowCardinality(String),
+    exchange_version LowCardinality(String),
+    check_name LowCardinality(String),
+    order_hash String,
+    expected String,
+    actual String
+)
+ENGINE = MergeTree()
+PARTITION BY toYYYYMM(block_timestamp)
+ORDER BY (check_name, block_number, id)
+SETTINGS index_granularity = 8192;
+
 -- Market Analytics Table
--- ReplacingMergeTree for handling updates
+-- Append-only rows written by db_out_clickhouse; ReplacingMergeTree keeps the highest version per id
 CREATE TABLE IF NOT EXISTS market_analytics (
     id String,
     condition_id String,
@@ -45,21 +95,29 @@ CREATE TABLE IF NOT EXISTS market_analytics (
     buys_quantity UInt64,
     sells_quantity UInt64,
     collateral_volume UInt256,
+    wash_adjusted_volume UInt256,
     average_trade_size Decimal(38, 18),
     total_fees UInt256,
     mid_price Decimal(38, 18),
+    best_bid Nullable(Decimal(38, 18)),
+    best_ask Nullable(Decimal(38, 18)),
+    spread Decimal(38, 18),
+    market_depth Decimal(76, 18),
+    volatility Decimal(38, 18),
     volume_24h Decimal(38, 18),
     volume_7d Decimal(38, 18),
     liquidity_score Decimal(38, 18),
     last_updated_block UInt64,
+    block_hash String,
+    version UInt64,                -- block number of the emitting block
     updated_at DateTime DEFAULT now()
 )
-ENGINE = ReplacingMergeTree(updated_at)
+ENGINE = ReplacingMergeTree(version)
 ORDER BY (id)
 SETTINGS index_granularity = 8192;
 
 -- Trader Analytics Table
--- ReplacingMergeTree for efficient updates
+-- Append-only rows written by db_out_clickhouse; ReplacingMergeTree keeps the highest version per id
 CREATE TABLE IF NOT EXISTS trader_analytics (
     id String,
     trades_quantity UInt64,
@@ -70,13 +128,34 @@ CREATE TABLE IF NOT EXISTS trader_analytics (
     markets_traded UInt64,
     is_active UInt8,
     trader_type LowCardinality(String),
+    block_hash String,
+    version UInt64,                -- block number of the emitting block
+    updated_at DateTime DEFAULT now()
+)
+ENGINE = ReplacingMergeTree(version)
+ORDER BY (id)
+SETTINGS index_granularity = 8192;
+
+-- Builder Stats Table
+-- Append-only rows written by db_out_clickhouse; ReplacingMergeTree keeps the highest version per id
+CREATE TABLE IF NOT EXISTS builder_stats (
+    id String,
+    trades_quantity UInt64,
+    collateral_volume UInt256,
+    total_fees UInt256,
+    unique_traders UInt64,
+    unique_markets UInt64,
+    last_active_block UInt64,
+    block_hash String,
+    version UInt64,                -- block number of the emitting block
     updated_at DateTime DEFAULT now()
 )
-ENGINE = ReplacingMergeTree(updated_at)
+ENGINE = ReplacingMergeTree(version)
 ORDER BY (id)
 SETTINGS index_granularity = 8192;
 
 -- Global Analytics Table
+-- Append-only rows written by db_out_clickhouse; ReplacingMergeTree keeps the highest version per id
 CREATE TABLE IF NOT EXISTS global_analytics (
     id String DEFAULT 'global',
     trades_quantity UInt64,
@@ -89,9 +168,11 @@ CREATE TABLE IF NOT EXISTS global_analytics (
     volume_24h Decimal(38, 18),
     volume_7d Decimal(38, 18),
     platform_fee_revenue UInt256,
+    block_hash String,
+    version UInt64,                -- block number of the emitting block
     updated_at DateTime DEFAULT now()
 )
-ENGINE = ReplacingMergeTree(updated_at)
+ENGINE = ReplacingMergeTree(version)
 ORDER BY (id)
 SETTINGS index_granularity = 8192;
 
@@ -117,7 +198,7 @@ SETTINGS index_granularity = 8192;
 CREATE MATERIALIZED VIEW IF NOT EXISTS mv_hourly_volume TO hourly_volume AS
 SELECT
     toStartOfHour(block_timestamp) AS hour,
-    maker_asset_id AS market_id,
+    concat('condition_', token_id) AS market_id,
     count() AS trades_count,
     sumIf(taker_amount_filled, side = 'buy') AS buy_volume,
     sumIf(taker_amount_filled, side = 'sell') AS sell_volume,
@@ -150,7 +231,7 @@ SETTINGS index_granularity = 8192;
 CREATE MATERIALIZED VIEW IF NOT EXISTS mv_daily_volume TO daily_volume AS
 SELECT
     toDate(block_timestamp) AS date,
-    maker_asset_id AS market_id,
+    concat('condition_', token_id) AS market_id,
     count() AS trades_count,
     sumIf(taker_amount_filled, side = 'buy') AS buy_volume,
     sumIf(taker_amount_filled, side = 'sell') AS sell_volume,
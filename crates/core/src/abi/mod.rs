@@ -0,0 +1,8 @@
+// Generated by build.rs with abigen; not held to the crate's clippy lints
+#![allow(clippy::all)]
+
+pub mod ctf_exchange;
+pub mod neg_risk_exchange;
+pub mod ctf_exchange_v2;
+pub mod neg_risk_exchange_v2;
+pub mod neg_risk_adapter;
@@ -0,0 +1,153 @@
+use substreams::Hex;
+use substreams_ethereum::pb::eth::v2 as eth;
+use substreams_ethereum::Event;
+
+use crate::abi;
+use crate::pb::polymarket::orderbook::v1::EventParam;
+
+/// Decode a non-trade v1 exchange log into `(event_type, params)`.
+/// CTF and Neg Risk v1 share these event signatures, so the CTF types decode both.
+pub fn decode_v1(log: &eth::Log) -> Option<(&'static str, Vec<EventParam>)> {
+    use abi::ctf_exchange::events as ev;
+
+    if let Some(e) = ev::TokenRegistered::match_and_decode(log) {
+        return Some(("TokenRegistered", vec![
+            param("token0", e.token0.to_string()),
+            param("token1", e.token1.to_string()),
+            param("condition_id", Hex::encode(e.condition_id)),
+        ]));
+    }
+    if let Some(e) = ev::FeeCharged::match_and_decode(log) {
+        return Some(("FeeCharged", vec![
+            param("receiver", Hex::encode(&e.receiver)),
+            param("token_id", e.token_id.to_string()),
+            param("amount", e.amount.to_string()),
+        ]));
+    }
+    if let Some(e) = ev::OrderCancelled::match_and_decode(log) {
+        return Some(("OrderCancelled", vec![param("order_hash", Hex::encode(e.order_hash))]));
+    }
+    if let Some(e) = ev::NewAdmin::match_and_decode(log) {
+        return Some(("NewAdmin", vec![
+            param("new_admin_address", Hex::encode(&e.new_admin_address)),
+            param("admin", Hex::encode(&e.admin)),
+        ]));
+    }
+    if let Some(e) = ev::NewOperator::match_and_decode(log) {
+        return Some(("NewOperator", vec![
+            param("new_operator_address", Hex::encode(&e.new_operator_address)),
+            param("admin", Hex::encode(&e.admin)),
+        ]));
+    }
+    if let Some(e) = ev::RemovedAdmin::match_and_decode(log) {
+        return Some(("RemovedAdmin", vec![
+            param("removed_admin", Hex::encode(&e.removed_admin)),
+            param("admin", Hex::encode(&e.admin)),
+        ]));
+    }
+    if let Some(e) = ev::RemovedOperator::match_and_decode(log) {
+        return Some(("RemovedOperator", vec![
+            param("removed_operator", Hex::encode(&e.removed_operator)),
+            param("admin", Hex::encode(&e.admin)),
+        ]));
+    }
+    if let Some(e) = ev::TradingPaused::match_and_decode(log) {
+        return Some(("TradingPaused", vec![param("pauser", Hex::encode(&e.pauser))]));
+    }
+    if let Some(e) = ev::TradingUnpaused::match_and_decode(log) {
+        return Some(("TradingUnpaused", vec![param("pauser", Hex::encode(&e.pauser))]));
+    }
+    if let Some(e) = ev::ProxyFactoryUpdated::match_and_decode(log) {
+        return Some(("ProxyFactoryUpdated", vec![
+            param("old_proxy_factory", Hex::encode(&e.old_proxy_factory)),
+            param("new_proxy_factory", Hex::encode(&e.new_proxy_factory)),
+        ]));
+    }
+    if let Some(e) = ev::SafeFactoryUpdated::match_and_decode(log) {
+        return Some(("SafeFactoryUpdated", vec![
+            param("old_safe_factory", Hex::encode(&e.old_safe_factory)),
+            param("new_safe_factory", Hex::encode(&e.new_safe_factory)),
+        ]));
+    }
+
+    None
+}
+
+/// Decode a non-trade v2 exchange log into `(event_type, params)`.
+/// CTF and Neg Risk v2 share these event signatures, so the CTF types decode both.
+pub fn decode_v2(log: &eth::Log) -> Option<(&'static str, Vec<EventParam>)> {
+    use abi::ctf_exchange_v2::events as ev;
+
+    if let Some(e) = ev::FeeCharged::match_and_decode(log) {
+        return Some(("FeeCharged", vec![
+            param("receiver", Hex::encode(&e.receiver)),
+            param("amount", e.amount.to_string()),
+        ]));
+    }
+    if let Some(e) = ev::FeeReceiverUpdated::match_and_decode(log) {
+        return Some(("FeeReceiverUpdated", vec![param("fee_receiver", Hex::encode(&e.fee_receiver))]));
+    }
+    if let Some(e) = ev::MaxFeeRateUpdated::match_and_decode(log) {
+        return Some(("MaxFeeRateUpdated", vec![param("max_fee_rate", e.max_fee_rate.to_string())]));
+    }
+    if let Some(e) = ev::NewAdmin::match_and_decode(log) {
+        return Some(("NewAdmin", vec![
+            param("new_admin_address", Hex::encode(&e.new_admin_address)),
+            param("admin", Hex::encode(&e.admin)),
+        ]));
+    }
+    if let Some(e) = ev::NewOperator::match_and_decode(log) {
+        return Some(("NewOperator", vec![
+            param("new_operator_address", Hex::encode(&e.new_operator_address)),
+            param("admin", Hex::encode(&e.admin)),
+        ]));
+    }
+    if let Some(e) = ev::RemovedAdmin::match_and_decode(log) {
+        return Some(("RemovedAdmin", vec![
+            param("removed_admin", Hex::encode(&e.removed_admin)),
+            param("admin", Hex::encode(&e.admin)),
+        ]));
+    }
+    if let Some(e) = ev::RemovedOperator::match_and_decode(log) {
+        return Some(("RemovedOperator", vec![
+            param("removed_operator", Hex::encode(&e.removed_operator)),
+            param("admin", Hex::encode(&e.admin)),
+        ]));
+    }
+    if let Some(e) = ev::OrderPreapproved::match_and_decode(log) {
+        return Some(("OrderPreapproved", vec![param("order_hash", Hex::encode(e.order_hash))]));
+    }
+    if let Some(e) = ev::OrderPreapprovalInvalidated::match_and_decode(log) {
+        return Some(("OrderPreapprovalInvalidated", vec![param("order_hash", Hex::encode(e.order_hash))]));
+    }
+    if let Some(e) = ev::TradingPaused::match_and_decode(log) {
+        return Some(("TradingPaused", vec![param("pauser", Hex::encode(&e.pauser))]));
+    }
+    if let Some(e) = ev::TradingUnpaused::match_and_decode(log) {
+        return Some(("TradingUnpaused", vec![param("pauser", Hex::encode(&e.pauser))]));
+    }
+    if let Some(e) = ev::UserPaused::match_and_decode(log) {
+        return Some(("UserPaused", vec![
+            param("user", Hex::encode(&e.user)),
+            param("effective_pause_block", e.effective_pause_block.to_string()),
+        ]));
+    }
+    if let Some(e) = ev::UserUnpaused::match_and_decode(log) {
+        return Some(("UserUnpaused", vec![param("user", Hex::encode(&e.user))]));
+    }
+    if let Some(e) = ev::UserPauseBlockIntervalUpdated::match_and_decode(log) {
+        return Some(("UserPauseBlockIntervalUpdated", vec![
+            param("old_interval", e.old_interval.to_string()),
+            param("new_interval", e.new_interval.to_string()),
+        ]));
+    }
+
+    None
+}
+
+fn param(name: &str, value: String) -> EventParam {
+    EventParam {
+        name: name.to_string(),
+        value,
+    }
+}
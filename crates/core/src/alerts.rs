@@ -0,0 +1,262 @@
+use bigdecimal::{BigDecimal, Signed, Zero};
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+use substreams::scalar::BigInt;
+
+use crate::pb::polymarket::orderbook::v1::market_alert::AlertType;
+use crate::pb::polymarket::orderbook::v1::{MarketAlert, OrderFilledEvents};
+use crate::utils;
+
+/// Activity is bucketed into windows of this length; a block is compared with the previous window
+pub const BASELINE_WINDOW_SECONDS: u64 = 3600;
+
+/// Per-token counters kept for every window, as `{window}:{metric}:{token_id}`
+pub const ACTIVITY_METRICS: [&str; 5] = ["volume", "tokens", "fills", "makers", "blocks"];
+
+/// Alert thresholds parsed from module params.
+///
+/// Params are whitespace-separated `key:value` terms, e.g.
+/// `volume_multiplier:5 price_change:0.1 liquidity_multiplier:3 large_trade_multiplier:10 min_baseline_fills:10`.
+/// Omitted keys keep these defaults.
+#[derive(Debug, Clone)]
+pub struct AlertThresholds {
+    /// Block volume over the baseline volume per active block
+    pub volume_multiplier: BigDecimal,
+    /// Absolute move of the last outcome price away from the baseline VWAP
+    pub price_change: BigDecimal,
+    /// Distinct makers filled in the block over the baseline makers per active block
+    pub liquidity_multiplier: BigDecimal,
+    /// Largest single fill over the baseline average fill
+    pub large_trade_multiplier: BigDecimal,
+    /// Tokens with fewer fills in the baseline window raise no alerts
+    pub min_baseline_fills: u64,
+}
+
+impl Default for AlertThresholds {
+    fn default() -> Self {
+        AlertThresholds {
+            volume_multiplier: BigDecimal::from(5),
+            price_change: BigDecimal::from_str("0.1").unwrap(),
+            liquidity_multiplier: BigDecimal::from(3),
+            large_trade_multiplier: BigDecimal::from(10),
+            min_baseline_fills: 10,
+        }
+    }
+}
+
+impl AlertThresholds {
+    pub fn parse(params: &str) -> Result<Self, substreams::errors::Error> {
+        let mut thresholds = AlertThresholds::default();
+
+        for term in params.split_whitespace() {
+            let (key, value) = term
+                .split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("invalid threshold term {:?}, expected key:value", term))?;
+            let decimal = || {
+                BigDecimal::from_str(value)
+                    .ok()
+                    .filter(|v| v.is_positive())
+                    .ok_or_else(|| anyhow::anyhow!("invalid {} {:?}, expected a positive number", key, value))
+            };
+
+            match key {
+                "volume_multiplier" => thresholds.volume_multiplier = decimal()?,
+                "price_change" => thresholds.price_change = decimal()?,
+                "liquidity_multiplier" => thresholds.liquidity_multiplier = decimal()?,
+                "large_trade_multiplier" => thresholds.large_trade_multiplier = decimal()?,
+                "min_baseline_fills" => {
+                    thresholds.min_baseline_fills = value
+                        .parse::<u64>()
+                        .map_err(|_| anyhow::anyhow!("invalid min_baseline_fills {:?}", value))?;
+                }
+                _ => return Err(anyhow::anyhow!("unknown threshold key {:?}", key)),
+            }
+        }
+
+        Ok(thresholds)
+    }
+}
+
+/// What one block traded in a token
+#[derive(Debug)]
+pub struct TokenActivity {
+    /// Raw collateral amount
+    pub volume: BigInt,
+    /// Raw outcome token amount
+    pub tokens: BigInt,
+    pub fills: u64,
+    pub makers: BTreeSet<String>,
+    /// Outcome price of the last fill by ordinal
+    pub last_price: BigDecimal,
+    /// Collateral of the largest fill
+    pub largest_fill: BigInt,
+}
+
+impl Default for TokenActivity {
+    fn default() -> Self {
+        TokenActivity {
+            volume: BigInt::zero(),
+            tokens: BigInt::zero(),
+            fills: 0,
+            makers: BTreeSet::new(),
+            last_price: BigDecimal::from(0),
+            largest_fill: BigInt::zero(),
+        }
+    }
+}
+
+/// Window a block timestamp falls in
+pub fn window_of(timestamp_seconds: u64) -> u64 {
+    timestamp_seconds / BASELINE_WINDOW_SECONDS
+}
+
+pub fn activity_key(metric: &str, token_id: &str, window: u64) -> String {
+    format!("{}:{}:{}", window, metric, token_id)
+}
+
+/// Key prefix of every counter of `window`, for pruning windows no block compares against
+pub fn window_prefix(window: u64) -> String {
+    format!("{}:", window)
+}
+
+/// Activity per token of one block's fills, keyed by token ID
+pub fn block_activity(fills: &OrderFilledEvents) -> BTreeMap<String, TokenActivity> {
+    let mut activity: BTreeMap<String, TokenActivity> = BTreeMap::new();
+
+    for fill in &fills.events {
+        let token = activity.entry(fill.token_id.clone()).or_default();
+        let collateral = amount(utils::collateral_amount(fill));
+
+        token.volume = token.volume.clone() + collateral.clone();
+        token.tokens = token.tokens.clone() + amount(utils::token_amount(fill));
+        token.fills += 1;
+        token.makers.insert(fill.maker.clone());
+        token.last_price = utils::outcome_price(fill);
+        if collateral > token.largest_fill {
+            token.largest_fill = collateral;
+        }
+    }
+
+    activity
+}
+
+/// Compares each token's activity in `fills` with the previous window's counters, read through
+/// `baseline(activity_key(..))`, and returns one alert per threshold crossed
+pub fn market_alerts(
+    thresholds: &AlertThresholds,
+    fills: &OrderFilledEvents,
+    baseline: impl Fn(&str) -> Option<BigInt>,
+) -> Vec<MarketAlert> {
+    let seconds = fills.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(0);
+    let previous = window_of(seconds).saturating_sub(1);
+    let min_baseline_fills = BigDecimal::from(thresholds.min_baseline_fills);
+    let mut alerts = vec![];
+
+    for (token_id, activity) in block_activity(fills) {
+        let metric = |name: &str| decimal(baseline(&activity_key(name, &token_id, previous)));
+        let base_fills = metric("fills");
+        if base_fills < min_baseline_fills || base_fills.is_zero() {
+            continue;
+        }
+        let (base_volume, base_tokens, base_makers, base_blocks) =
+            (metric("volume"), metric("tokens"), metric("makers"), metric("blocks"));
+
+        let mut alert = |alert_type: AlertType, severity: String, message: String, value: String, base: String| {
+            alerts.push(MarketAlert {
+                r#type: alert_type as i32,
+                market_id: utils::extract_condition_id_from_str(&token_id),
+                message,
+                severity,
+                timestamp: fills.timestamp,
+                value,
+                baseline: base,
+                block_number: fills.block_number,
+            })
+        };
+
+        if base_blocks.is_positive() {
+            let volume = decimal(Some(activity.volume.clone()));
+            let per_block = &base_volume / &base_blocks;
+            if per_block.is_positive() {
+                let ratio = &volume / &per_block;
+                if ratio >= thresholds.volume_multiplier {
+                    alert(
+                        AlertType::VolumeSpike,
+                        severity(&ratio / &thresholds.volume_multiplier),
+                        format!("block volume {} is {}x the previous hour's {} per active block", volume, ratio.round(2), per_block.round(0)),
+                        volume.to_string(),
+                        per_block.round(0).to_string(),
+                    );
+                }
+            }
+
+            let makers = BigDecimal::from(activity.makers.len() as u64);
+            let per_block = &base_makers / &base_blocks;
+            if per_block.is_positive() {
+                let ratio = &makers / &per_block;
+                if ratio >= thresholds.liquidity_multiplier {
+                    alert(
+                        AlertType::LiquidityChange,
+                        severity(&ratio / &thresholds.liquidity_multiplier),
+                        format!("{} makers filled in one block, {}x the previous hour's {} per active block", makers, ratio.round(2), per_block.round(2)),
+                        makers.to_string(),
+                        per_block.round(2).to_string(),
+                    );
+                }
+            }
+        }
+
+        if base_tokens.is_positive() {
+            let vwap = &base_volume / &base_tokens;
+            let change = (&activity.last_price - &vwap).abs();
+            if change >= thresholds.price_change {
+                alert(
+                    AlertType::PriceMovement,
+                    severity(&change / &thresholds.price_change),
+                    format!("price {} moved {} from the previous hour's VWAP {}", activity.last_price.round(4), change.round(4), vwap.round(4)),
+                    activity.last_price.round(4).to_string(),
+                    vwap.round(4).to_string(),
+                );
+            }
+        }
+
+        let average_fill = &base_volume / &base_fills;
+        if average_fill.is_positive() {
+            let largest = decimal(Some(activity.largest_fill.clone()));
+            let ratio = &largest / &average_fill;
+            if ratio >= thresholds.large_trade_multiplier {
+                alert(
+                    AlertType::UnusualActivity,
+                    severity(&ratio / &thresholds.large_trade_multiplier),
+                    format!("single fill of {} is {}x the previous hour's average fill {}", largest, ratio.round(2), average_fill.round(0)),
+                    largest.to_string(),
+                    average_fill.round(0).to_string(),
+                );
+            }
+        }
+    }
+
+    alerts
+}
+
+/// `"low"` at the threshold, `"medium"` at twice it, `"high"` at four times it
+fn severity(over_threshold: BigDecimal) -> String {
+    let (medium, high) = (BigDecimal::from(2), BigDecimal::from(4));
+    if over_threshold >= high {
+        "high".to_string()
+    } else if over_threshold >= medium {
+        "medium".to_string()
+    } else {
+        "low".to_string()
+    }
+}
+
+fn amount(value: &str) -> BigInt {
+    BigInt::from_str(value).unwrap_or_else(|_| BigInt::zero())
+}
+
+fn decimal(value: Option<BigInt>) -> BigDecimal {
+    value
+        .and_then(|v| BigDecimal::from_str(&v.to_string()).ok())
+        .unwrap_or_default()
+}
@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use substreams::scalar::BigInt;
+
+use crate::pb::polymarket::orderbook::v1::{OrderFilledEvent, OrderFilledEvents};
+use crate::utils;
+
+/// Counters kept per builder, as `builder:{builder}:{metric}`
+pub const BUILDER_METRICS: [&str; 5] = ["trades", "volume", "fees", "traders", "markets"];
+
+/// Builder code of a fill, or None for v1 fills and v2 orders signed without one (all zeros)
+pub fn builder_code(fill: &OrderFilledEvent) -> Option<&str> {
+    let builder = fill.builder.as_str();
+    if builder.is_empty() || builder.chars().all(|c| c == '0') {
+        None
+    } else {
+        Some(builder)
+    }
+}
+
+pub fn metric_key(builder: &str, metric: &str) -> String {
+    format!("builder:{}:{}", builder, metric)
+}
+
+/// Membership keys of `store_builder_members`: the builder's order makers and traded tokens
+pub fn trader_key(builder: &str, trader: &str) -> String {
+    format!("{}:trader:{}", builder, trader)
+}
+
+pub fn market_key(builder: &str, token_id: &str) -> String {
+    format!("{}:market:{}", builder, token_id)
+}
+
+/// Builder a membership key belongs to and whether it is a `trader` or `market` key
+pub fn parse_member_key(key: &str) -> Option<(&str, &str)> {
+    let mut parts = key.splitn(3, ':');
+    let builder = parts.next()?;
+    let kind = parts.next()?;
+    parts.next()?;
+    Some((builder, kind))
+}
+
+/// Trades, raw collateral volume and raw fees of one block's fills per builder
+pub fn block_totals(fills: &OrderFilledEvents) -> BTreeMap<String, (u64, BigInt, BigInt)> {
+    let mut totals: BTreeMap<String, (u64, BigInt, BigInt)> = BTreeMap::new();
+
+    for fill in &fills.events {
+        let Some(builder) = builder_code(fill) else {
+            continue;
+        };
+        let total = totals
+            .entry(builder.to_string())
+            .or_insert_with(|| (0, BigInt::zero(), BigInt::zero()));
+        total.0 += 1;
+        total.1 = total.1.clone() + amount(utils::collateral_amount(fill));
+        total.2 = total.2.clone() + amount(&fill.fee);
+    }
+
+    totals
+}
+
+fn amount(value: &str) -> BigInt {
+    BigInt::from_str(value).unwrap_or_else(|_| BigInt::zero())
+}
@@ -0,0 +1,57 @@
+use bigdecimal::BigDecimal;
+use prost_types::Timestamp;
+use std::str::FromStr;
+
+use crate::pb::polymarket::orderbook::v1::{ConditionPrice, RegisteredToken, TokenPrice};
+
+/// Implied YES probability of `token`'s condition from the last prices of its YES and NO tokens.
+///
+/// With both prices known the probability is the mean of `yes` and `1 - no`, which cancels a
+/// uniform spread between the two books; with one known it is that price's implication.
+pub fn condition_price(
+    token: &RegisteredToken,
+    yes: Option<TokenPrice>,
+    no: Option<TokenPrice>,
+    block_number: u64,
+    timestamp: Option<Timestamp>,
+) -> ConditionPrice {
+    let (yes_token_id, no_token_id) = if token.outcome == "no" {
+        (token.complement_token_id.clone(), token.token_id.clone())
+    } else {
+        (token.token_id.clone(), token.complement_token_id.clone())
+    };
+    let yes_price = yes.map(|p| price(&p));
+    let no_price = no.map(|p| price(&p));
+
+    let implied = match (&yes_price, &no_price) {
+        (Some(yes), Some(no)) => Some((yes + (BigDecimal::from(1) - no)) / BigDecimal::from(2)),
+        (Some(yes), None) => Some(yes.clone()),
+        (None, Some(no)) => Some(BigDecimal::from(1) - no),
+        (None, None) => None,
+    };
+    let sum = match (&yes_price, &no_price) {
+        (Some(yes), Some(no)) => Some(yes + no),
+        _ => None,
+    };
+
+    ConditionPrice {
+        condition_id: token.condition_id.clone(),
+        yes_token_id,
+        no_token_id,
+        yes_price: display(&yes_price),
+        no_price: display(&no_price),
+        implied_yes_probability: display(&implied),
+        deviation: display(&sum.as_ref().map(|s| s - BigDecimal::from(1))),
+        price_sum: display(&sum),
+        block_number,
+        timestamp,
+    }
+}
+
+fn price(token_price: &TokenPrice) -> BigDecimal {
+    BigDecimal::from_str(&token_price.price).unwrap_or_default()
+}
+
+fn display(value: &Option<BigDecimal>) -> String {
+    value.as_ref().map(|v| v.to_string()).unwrap_or_default()
+}
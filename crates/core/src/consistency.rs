@@ -0,0 +1,145 @@
+use bigdecimal::{BigDecimal, One, Signed};
+use std::str::FromStr;
+
+use crate::pb::polymarket::orderbook::v1::{ConsistencyViolation, ExchangeEvents, OrderFilledEvent, OrdersMatchedEvent};
+use crate::utils;
+
+/// Checks the fills and matches of every transaction in `events`.
+///
+/// A match emits one OrderFilled per maker order, then the taker's OrderFilled (whose taker is the
+/// exchange itself), then OrdersMatched. Per v2 match:
+/// - `taker_fill_vs_match`: the taker fill carries the OrdersMatched amounts
+/// - `maker_fill_sum`: the token legs of the maker fills add up to the taker's token leg
+/// - `missing_taker_fill`: no taker fill precedes the OrdersMatched
+///
+/// v1 matches are skipped: the bundled v1 OrdersMatched ABI has not been checked against logs
+/// the deployed v1 exchanges emit.
+///
+/// Per fill:
+/// - `price_out_of_range`: collateral per outcome token falls outside [0, 1]
+/// - `fee_above_max_rate`: a v2 fee exceeds `max_fee_rate_bps(exchange)` basis points of the token leg
+pub fn check_consistency(
+    events: &ExchangeEvents,
+    max_fee_rate_bps: impl Fn(&str) -> Option<BigDecimal>,
+) -> Vec<ConsistencyViolation> {
+    let mut violations = vec![];
+
+    for fill in &events.order_fills {
+        let price = utils::outcome_price(fill);
+        if price.is_negative() || price > BigDecimal::one() {
+            violations.push(fill_violation(fill, "price_out_of_range", "[0, 1]".to_string(), price.to_string()));
+        }
+
+        if fill.exchange_version == "v2" {
+            if let Some(rate) = max_fee_rate_bps(&fill.exchange) {
+                let max_fee = rate * amount(utils::token_amount(fill)) / BigDecimal::from(10_000);
+                if amount(&fill.fee) > max_fee {
+                    violations.push(fill_violation(fill, "fee_above_max_rate", format!("<= {}", max_fee), fill.fee.clone()));
+                }
+            }
+        }
+    }
+
+    for matched in events.orders_matched.iter().filter(|m| m.exchange_version == "v2") {
+        let fills = fills_of_match(events, matched);
+
+        let Some(taker_fill) = fills.iter().rev().find(|f| f.taker == f.call_address) else {
+            violations.push(match_violation(
+                matched,
+                &matched.taker_order_hash,
+                "missing_taker_fill",
+                "taker OrderFilled".to_string(),
+                "none".to_string(),
+            ));
+            continue;
+        };
+
+        let expected = format!("{}/{}", matched.maker_amount_filled, matched.taker_amount_filled);
+        let actual = format!("{}/{}", taker_fill.maker_amount_filled, taker_fill.taker_amount_filled);
+        if expected != actual {
+            violations.push(match_violation(matched, &taker_fill.order_hash, "taker_fill_vs_match", expected, actual));
+        }
+
+        let maker_tokens: BigDecimal = fills
+            .iter()
+            .filter(|f| f.ordinal < taker_fill.ordinal && f.taker == taker_fill.maker)
+            .map(|f| amount(utils::token_amount(f)))
+            .sum();
+        let taker_tokens = amount(utils::token_amount(taker_fill));
+        if maker_tokens != taker_tokens {
+            violations.push(match_violation(
+                matched,
+                &taker_fill.order_hash,
+                "maker_fill_sum",
+                taker_tokens.to_string(),
+                maker_tokens.to_string(),
+            ));
+        }
+    }
+
+    violations.sort_by_key(|v| v.ordinal);
+    violations
+}
+
+/// Fills of the same transaction and exchange emitted after the previous match and before `matched`
+fn fills_of_match<'a>(events: &'a ExchangeEvents, matched: &OrdersMatchedEvent) -> Vec<&'a OrderFilledEvent> {
+    let same_exchange = |hash: &str, exchange: &str, version: &str| {
+        hash == matched.transaction_hash && exchange == matched.exchange && version == matched.exchange_version
+    };
+    let previous_match = events
+        .orders_matched
+        .iter()
+        .filter(|m| same_exchange(&m.transaction_hash, &m.exchange, &m.exchange_version) && m.ordinal < matched.ordinal)
+        .map(|m| m.ordinal)
+        .max()
+        .unwrap_or(0);
+
+    events
+        .order_fills
+        .iter()
+        .filter(|f| same_exchange(&f.transaction_hash, &f.exchange, &f.exchange_version))
+        .filter(|f| f.ordinal > previous_match && f.ordinal < matched.ordinal)
+        .collect()
+}
+
+fn amount(value: &str) -> BigDecimal {
+    BigDecimal::from_str(value).unwrap_or_default()
+}
+
+fn fill_violation(fill: &OrderFilledEvent, check: &str, expected: String, actual: String) -> ConsistencyViolation {
+    ConsistencyViolation {
+        id: format!("{}-{}-{}", fill.transaction_hash, fill.ordinal, check),
+        transaction_hash: fill.transaction_hash.clone(),
+        timestamp: fill.timestamp,
+        block_number: fill.block_number,
+        ordinal: fill.ordinal,
+        exchange: fill.exchange.clone(),
+        exchange_version: fill.exchange_version.clone(),
+        check: check.to_string(),
+        order_hash: fill.order_hash.clone(),
+        expected,
+        actual,
+    }
+}
+
+fn match_violation(
+    matched: &OrdersMatchedEvent,
+    order_hash: &str,
+    check: &str,
+    expected: String,
+    actual: String,
+) -> ConsistencyViolation {
+    ConsistencyViolation {
+        id: format!("{}-{}-{}", matched.transaction_hash, matched.ordinal, check),
+        transaction_hash: matched.transaction_hash.clone(),
+        timestamp: matched.timestamp,
+        block_number: matched.block_number,
+        ordinal: matched.ordinal,
+        exchange: matched.exchange.clone(),
+        exchange_version: matched.exchange_version.clone(),
+        check: check.to_string(),
+        order_hash: order_hash.to_string(),
+        expected,
+        actual,
+    }
+}
@@ -0,0 +1,515 @@
+use prost_types::Timestamp;
+use std::sync::OnceLock;
+use substreams::pb::sf::substreams::index::v1::Keys;
+use substreams::Hex;
+use substreams_ethereum::pb::eth::v2 as eth;
+use substreams_ethereum::Event;
+
+use crate::pb::polymarket::orderbook::v1::{
+    AdminEvent, EventParam, ExchangeEvents, OrderFilledEvent, OrdersMatchedEvent, UndecodedLog,
+};
+use crate::metadata::MetadataDecoder;
+use crate::{abi, admin_events, registry, utils};
+
+/// Block index keys for every receipt log of a successful transaction emitted by an exchange in `registry`:
+/// `evt_addr:0x{address}`, `evt_sig:0x{topic0}`, `exchange:{kind}_{version}` and `exchange:any`.
+pub fn exchange_index_keys(registry: &registry::ContractRegistry, blk: &eth::Block) -> Keys {
+    let mut keys = Keys::default();
+
+    for log in blk.logs() {
+        let Some(exchange) = registry.find(log.address(), blk.number) else {
+            continue;
+        };
+
+        let mut log_keys = vec![
+            "exchange:any".to_string(),
+            format!("exchange:{}_{}", exchange.kind, exchange.version),
+            format!("evt_addr:0x{}", exchange.address),
+        ];
+        if let Some(topic0) = log.topics().first() {
+            log_keys.push(format!("evt_sig:0x{}", Hex::encode(topic0)));
+        }
+
+        for key in log_keys {
+            if !keys.keys.contains(&key) {
+                keys.keys.push(key);
+            }
+        }
+    }
+
+    keys
+}
+
+/// Scans the receipt logs of every successful transaction once, the same logs
+/// `exchange_index_keys` indexes. Logs from an exchange in `registry` are dispatched by
+/// address to the v1 or v2 decoders and emitted as fills, matches or admin events, sorted by ordinal.
+/// Logs no decoder accepts are kept in `undecoded_logs` rather than dropped.
+pub fn extract_exchange_events(registry: &registry::ContractRegistry, blk: &eth::Block) -> ExchangeEvents {
+    let mut output = ExchangeEvents {
+        order_fills: vec![],
+        orders_matched: vec![],
+        admin_events: vec![],
+        block_number: blk.number,
+        block_hash: Hex::encode(&blk.hash),
+        timestamp: Some(Timestamp {
+            seconds: blk.timestamp_seconds() as i64,
+            nanos: 0,
+        }),
+        undecoded_logs: vec![],
+    };
+
+    for view in blk.logs() {
+        let (trx, log) = (view.receipt.transaction, view.log);
+        let Some(exchange) = registry.find(&log.address, blk.number) else {
+            continue;
+        };
+        let call = emitting_call(trx, log);
+        let src = LogSource { blk, trx, call, log, exchange };
+
+        let decoded = match (exchange.kind.as_str(), exchange.version.as_str()) {
+            ("ctf", "v1") => decode_v1_log::<
+                abi::ctf_exchange::events::OrderFilled,
+                abi::ctf_exchange::events::OrdersMatched,
+            >(&src, &mut output),
+            ("neg_risk", "v1") => decode_v1_log::<
+                abi::neg_risk_exchange::events::OrderFilled,
+                abi::neg_risk_exchange::events::OrdersMatched,
+            >(&src, &mut output),
+            ("ctf", "v2") => decode_v2_log::<
+                abi::ctf_exchange_v2::events::OrderFilled,
+                abi::ctf_exchange_v2::events::OrdersMatched,
+            >(&src, &mut output),
+            ("neg_risk", "v2") => decode_v2_log::<
+                abi::neg_risk_exchange_v2::events::OrderFilled,
+                abi::neg_risk_exchange_v2::events::OrdersMatched,
+            >(&src, &mut output),
+            // `ContractRegistry::from_params` only admits the generations above
+            _ => false,
+        };
+
+        if !decoded {
+            output.undecoded_logs.push(undecoded_log(&src));
+        }
+    }
+
+    output.order_fills.sort_by_key(|e| e.ordinal);
+    output.orders_matched.sort_by_key(|e| e.ordinal);
+    output.admin_events.sort_by_key(|e| e.ordinal);
+    output.undecoded_logs.sort_by_key(|e| e.ordinal);
+
+    output
+}
+
+/// A log together with the block, transaction, call and exchange it came from
+struct LogSource<'a> {
+    blk: &'a eth::Block,
+    trx: &'a eth::TransactionTrace,
+    call: Option<&'a eth::Call>,
+    log: &'a eth::Log,
+    exchange: &'a registry::ExchangeContract,
+}
+
+impl LogSource<'_> {
+    fn timestamp(&self) -> Option<Timestamp> {
+        Some(Timestamp {
+            seconds: self.blk.timestamp_seconds() as i64,
+            nanos: 0,
+        })
+    }
+}
+
+/// The non-reverted call that emitted `log`, matched by its block-wide index
+fn emitting_call<'a>(trx: &'a eth::TransactionTrace, log: &eth::Log) -> Option<&'a eth::Call> {
+    trx.calls
+        .iter()
+        .filter(|call| !call.state_reverted)
+        .find(|call| call.logs.iter().any(|l| l.block_index == log.block_index))
+}
+
+/// Returns false when no v1 event decoded the log
+fn decode_v1_log<F, M>(src: &LogSource, output: &mut ExchangeEvents) -> bool
+where
+    F: Event + ExchangeFill,
+    M: Event + V1OrdersMatched,
+{
+    if let Some(event) = F::match_and_decode(src.log) {
+        output.order_fills.push(order_filled_event(src, &event));
+    } else if let Some(event) = M::match_and_decode(src.log) {
+        output.orders_matched.push(v1_orders_matched_event(src, &event));
+    } else if let Some((event_type, params)) = admin_events::decode_v1(src.log) {
+        output.admin_events.push(admin_event(src, event_type, params));
+    } else {
+        return false;
+    }
+    true
+}
+
+/// Returns false when no v2 event decoded the log
+fn decode_v2_log<F, M>(src: &LogSource, output: &mut ExchangeEvents) -> bool
+where
+    F: Event + ExchangeFill,
+    M: Event + V2OrdersMatched,
+{
+    if let Some(event) = F::match_and_decode(src.log) {
+        output.order_fills.push(order_filled_event(src, &event));
+    } else if let Some(event) = M::match_and_decode(src.log) {
+        output.orders_matched.push(v2_orders_matched_event(src, &event));
+    } else if let Some((event_type, params)) = admin_events::decode_v2(src.log) {
+        output.admin_events.push(admin_event(src, event_type, params));
+    } else {
+        return false;
+    }
+    true
+}
+
+/// Normalize any exchange generation's OrderFilled event into an `OrderFilledEvent`
+fn order_filled_event<E: ExchangeFill>(src: &LogSource, event: &E) -> OrderFilledEvent {
+    let ctx = TxContext::new(src.trx, src.call);
+    let order_id = utils::generate_order_id(&Hex::encode(&src.trx.hash), &Hex::encode(event.order_hash()));
+
+    let mut fill = OrderFilledEvent {
+        id: order_id,
+        transaction_hash: Hex::encode(&src.trx.hash),
+        timestamp: src.timestamp(),
+        order_hash: Hex::encode(event.order_hash()),
+        maker: Hex::encode(event.maker()),
+        taker: Hex::encode(event.taker()),
+        maker_asset_id: event.maker_asset_id(),
+        taker_asset_id: event.taker_asset_id(),
+        maker_amount_filled: event.maker_amount_filled().to_string(),
+        taker_amount_filled: event.taker_amount_filled().to_string(),
+        fee: event.fee().to_string(),
+        block_number: src.blk.number,
+        side: event.side(),
+        price: utils::calculate_price(event.maker_amount_filled(), event.taker_amount_filled()).to_string(),
+        ordinal: src.log.ordinal,
+        log_index: src.log.block_index,
+        exchange_version: E::VERSION.to_string(),
+        exchange: src.exchange.kind.clone(),
+        token_id: event.token_id(),
+        side_raw: event.side_raw(),
+        builder: Hex::encode(event.builder()),
+        metadata: Hex::encode(event.metadata()),
+        tx_from: ctx.tx_from,
+        tx_to: ctx.tx_to,
+        tx_index: ctx.tx_index,
+        gas_used: ctx.gas_used,
+        effective_gas_price: ctx.effective_gas_price,
+        call_address: ctx.call_address,
+        metadata_layout: String::new(),
+        metadata_fields: vec![],
+    };
+    MetadataDecoder::default().apply(&mut fill);
+    fill
+}
+
+fn v1_orders_matched_event<E: V1OrdersMatched>(src: &LogSource, event: &E) -> OrdersMatchedEvent {
+    let ctx = TxContext::new(src.trx, src.call);
+
+    OrdersMatchedEvent {
+        id: format!("{}-{}", Hex::encode(&src.trx.hash), src.log.ordinal),
+        timestamp: src.timestamp(),
+        maker_asset_id: event.maker_asset_id().to_string(),
+        taker_asset_id: event.taker_asset_id().to_string(),
+        maker_amount_filled: event.maker_amount_filled().to_string(),
+        taker_amount_filled: event.taker_amount_filled().to_string(),
+        block_number: src.blk.number,
+        ordinal: src.log.ordinal,
+        exchange_version: "v1".to_string(),
+        exchange: src.exchange.kind.clone(),
+        taker_order_hash: String::new(),
+        taker_order_maker: String::new(),
+        token_id: String::new(),
+        side_raw: 0,
+        tx_from: ctx.tx_from,
+        tx_to: ctx.tx_to,
+        tx_index: ctx.tx_index,
+        gas_used: ctx.gas_used,
+        effective_gas_price: ctx.effective_gas_price,
+        call_address: ctx.call_address,
+        transaction_hash: Hex::encode(&src.trx.hash),
+    }
+}
+
+fn v2_orders_matched_event<E: V2OrdersMatched>(src: &LogSource, event: &E) -> OrdersMatchedEvent {
+    let ctx = TxContext::new(src.trx, src.call);
+    let token_id = event.token_id().to_string();
+    let side_raw = event.side();
+    let (maker_asset_id, taker_asset_id) = utils::v2_assets_from_side(side_raw, &token_id);
+
+    OrdersMatchedEvent {
+        id: format!("{}-{}", Hex::encode(&src.trx.hash), src.log.ordinal),
+        timestamp: src.timestamp(),
+        maker_asset_id,
+        taker_asset_id,
+        maker_amount_filled: event.maker_amount_filled().to_string(),
+        taker_amount_filled: event.taker_amount_filled().to_string(),
+        block_number: src.blk.number,
+        ordinal: src.log.ordinal,
+        exchange_version: "v2".to_string(),
+        exchange: src.exchange.kind.clone(),
+        taker_order_hash: Hex::encode(event.taker_order_hash()),
+        taker_order_maker: Hex::encode(event.taker_order_maker()),
+        token_id,
+        side_raw: side_raw as u32,
+        tx_from: ctx.tx_from,
+        tx_to: ctx.tx_to,
+        tx_index: ctx.tx_index,
+        gas_used: ctx.gas_used,
+        effective_gas_price: ctx.effective_gas_price,
+        call_address: ctx.call_address,
+        transaction_hash: Hex::encode(&src.trx.hash),
+    }
+}
+
+fn admin_event(src: &LogSource, event_type: &str, params: Vec<EventParam>) -> AdminEvent {
+    AdminEvent {
+        id: format!("{}-{}", Hex::encode(&src.trx.hash), src.log.ordinal),
+        transaction_hash: Hex::encode(&src.trx.hash),
+        timestamp: src.timestamp(),
+        block_number: src.blk.number,
+        ordinal: src.log.ordinal,
+        exchange: src.exchange.kind.clone(),
+        exchange_version: src.exchange.version.clone(),
+        event_type: event_type.to_string(),
+        params,
+        tx_from: Hex::encode(&src.trx.from),
+    }
+}
+
+/// A log no decoder accepted: `decode_failed` when its topic0 belongs to an event of the
+/// exchange's ABI (so the layout changed), `unknown_topic` otherwise (so the ABI gained an event)
+fn undecoded_log(src: &LogSource) -> UndecodedLog {
+    let topic0 = src.log.topics.first().map(|t| t.as_slice()).unwrap_or_default();
+    let event_name = abi_event_name(src.exchange, topic0);
+
+    UndecodedLog {
+        id: format!("{}-{}", Hex::encode(&src.trx.hash), src.log.ordinal),
+        transaction_hash: Hex::encode(&src.trx.hash),
+        timestamp: src.timestamp(),
+        block_number: src.blk.number,
+        ordinal: src.log.ordinal,
+        log_index: src.log.block_index,
+        exchange: src.exchange.kind.clone(),
+        exchange_version: src.exchange.version.clone(),
+        address: Hex::encode(&src.log.address),
+        topics: src.log.topics.iter().map(Hex::encode).collect(),
+        data: Hex::encode(&src.log.data),
+        reason: if event_name.is_some() { "decode_failed" } else { "unknown_topic" }.to_string(),
+        event_name: event_name.unwrap_or_default().to_string(),
+    }
+}
+
+/// Name of the event with this topic0 in the ABI of `exchange`
+fn abi_event_name(exchange: &registry::ExchangeContract, topic0: &[u8]) -> Option<&'static str> {
+    abi_events()
+        .iter()
+        .find(|e| e.kind == exchange.kind && e.version == exchange.version && e.topic[..] == *topic0)
+        .map(|e| e.name.as_str())
+}
+
+/// An event of one of the bundled exchange ABIs
+struct AbiEvent {
+    kind: &'static str,
+    version: &'static str,
+    topic: [u8; 32],
+    name: String,
+}
+
+/// Every event of the bundled exchange ABIs, parsed on first use
+fn abi_events() -> &'static [AbiEvent] {
+    static EVENTS: OnceLock<Vec<AbiEvent>> = OnceLock::new();
+
+    EVENTS.get_or_init(|| {
+        [
+            ("ctf", "v1", include_str!("../../../abis/ctf_exchange.json")),
+            ("neg_risk", "v1", include_str!("../../../abis/neg_risk_exchange.json")),
+            ("ctf", "v2", include_str!("../../../abis/ctf_exchange_v2.json")),
+            ("neg_risk", "v2", include_str!("../../../abis/neg_risk_exchange_v2.json")),
+        ]
+        .into_iter()
+        .flat_map(|(kind, version, json)| {
+            let contract = ethabi::Contract::load(json.as_bytes()).expect("bundled exchange ABI");
+            contract
+                .events()
+                .map(|event| AbiEvent { kind, version, topic: event.signature().0, name: event.name.clone() })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+    })
+}
+
+trait V1OrdersMatched {
+    fn maker_asset_id(&self) -> &substreams::scalar::BigInt;
+    fn taker_asset_id(&self) -> &substreams::scalar::BigInt;
+    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt;
+    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt;
+}
+
+impl V1OrdersMatched for abi::ctf_exchange::events::OrdersMatched {
+    fn maker_asset_id(&self) -> &substreams::scalar::BigInt { &self.maker_asset_id }
+    fn taker_asset_id(&self) -> &substreams::scalar::BigInt { &self.taker_asset_id }
+    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.maker_amount_filled }
+    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.taker_amount_filled }
+}
+
+impl V1OrdersMatched for abi::neg_risk_exchange::events::OrdersMatched {
+    fn maker_asset_id(&self) -> &substreams::scalar::BigInt { &self.maker_asset_id }
+    fn taker_asset_id(&self) -> &substreams::scalar::BigInt { &self.taker_asset_id }
+    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.maker_amount_filled }
+    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.taker_amount_filled }
+}
+
+/// Normalized view over an exchange generation's OrderFilled event. Abigen generates a distinct
+/// struct per ABI even when fields match, so each of the four types implements this; supporting a
+/// new exchange version means adding an impl and a dispatch arm in `extract_exchange_events`.
+pub trait ExchangeFill {
+    /// `"v1"` | `"v2"`
+    const VERSION: &'static str;
+
+    fn order_hash(&self) -> &[u8];
+    fn maker(&self) -> &[u8];
+    fn taker(&self) -> &[u8];
+    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt;
+    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt;
+    fn fee(&self) -> &substreams::scalar::BigInt;
+    /// Asset IDs in v1 terms ("0" is collateral)
+    fn maker_asset_id(&self) -> String;
+    fn taker_asset_id(&self) -> String;
+    /// The conditional token traded
+    fn token_id(&self) -> String;
+    /// `"buy"` | `"sell"` | `"unknown"`
+    fn side(&self) -> String;
+    /// Raw side enum as emitted (v2 only)
+    fn side_raw(&self) -> u32 { 0 }
+    /// bytes32 builder code (v2 only)
+    fn builder(&self) -> &[u8] { &[] }
+    /// bytes32 order metadata (v2 only)
+    fn metadata(&self) -> &[u8] { &[] }
+}
+
+impl ExchangeFill for abi::ctf_exchange::events::OrderFilled {
+    const VERSION: &'static str = "v1";
+
+    fn order_hash(&self) -> &[u8] { &self.order_hash }
+    fn maker(&self) -> &[u8] { &self.maker }
+    fn taker(&self) -> &[u8] { &self.taker }
+    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.maker_amount_filled }
+    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.taker_amount_filled }
+    fn fee(&self) -> &substreams::scalar::BigInt { &self.fee }
+    fn maker_asset_id(&self) -> String { self.maker_asset_id.to_string() }
+    fn taker_asset_id(&self) -> String { self.taker_asset_id.to_string() }
+    fn token_id(&self) -> String { utils::v1_token_id(&self.maker_asset_id, &self.taker_asset_id) }
+    fn side(&self) -> String {
+        utils::determine_trade_side(&self.maker_asset_id, &self.taker_asset_id, &self.maker_amount_filled, &self.taker_amount_filled)
+    }
+}
+
+impl ExchangeFill for abi::neg_risk_exchange::events::OrderFilled {
+    const VERSION: &'static str = "v1";
+
+    fn order_hash(&self) -> &[u8] { &self.order_hash }
+    fn maker(&self) -> &[u8] { &self.maker }
+    fn taker(&self) -> &[u8] { &self.taker }
+    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.maker_amount_filled }
+    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.taker_amount_filled }
+    fn fee(&self) -> &substreams::scalar::BigInt { &self.fee }
+    fn maker_asset_id(&self) -> String { self.maker_asset_id.to_string() }
+    fn taker_asset_id(&self) -> String { self.taker_asset_id.to_string() }
+    fn token_id(&self) -> String { utils::v1_token_id(&self.maker_asset_id, &self.taker_asset_id) }
+    fn side(&self) -> String {
+        utils::determine_trade_side(&self.maker_asset_id, &self.taker_asset_id, &self.maker_amount_filled, &self.taker_amount_filled)
+    }
+}
+
+impl ExchangeFill for abi::ctf_exchange_v2::events::OrderFilled {
+    const VERSION: &'static str = "v2";
+
+    fn order_hash(&self) -> &[u8] { &self.order_hash }
+    fn maker(&self) -> &[u8] { &self.maker }
+    fn taker(&self) -> &[u8] { &self.taker }
+    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.maker_amount_filled }
+    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.taker_amount_filled }
+    fn fee(&self) -> &substreams::scalar::BigInt { &self.fee }
+    fn maker_asset_id(&self) -> String { utils::v2_assets_from_side(self.side_raw() as u8, &self.token_id.to_string()).0 }
+    fn taker_asset_id(&self) -> String { utils::v2_assets_from_side(self.side_raw() as u8, &self.token_id.to_string()).1 }
+    fn token_id(&self) -> String { self.token_id.to_string() }
+    fn side(&self) -> String { utils::v2_side_str(self.side_raw() as u8).to_string() }
+    fn side_raw(&self) -> u32 { self.side.to_u64() as u32 }
+    fn builder(&self) -> &[u8] { &self.builder }
+    fn metadata(&self) -> &[u8] { &self.metadata }
+}
+
+impl ExchangeFill for abi::neg_risk_exchange_v2::events::OrderFilled {
+    const VERSION: &'static str = "v2";
+
+    fn order_hash(&self) -> &[u8] { &self.order_hash }
+    fn maker(&self) -> &[u8] { &self.maker }
+    fn taker(&self) -> &[u8] { &self.taker }
+    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.maker_amount_filled }
+    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.taker_amount_filled }
+    fn fee(&self) -> &substreams::scalar::BigInt { &self.fee }
+    fn maker_asset_id(&self) -> String { utils::v2_assets_from_side(self.side_raw() as u8, &self.token_id.to_string()).0 }
+    fn taker_asset_id(&self) -> String { utils::v2_assets_from_side(self.side_raw() as u8, &self.token_id.to_string()).1 }
+    fn token_id(&self) -> String { self.token_id.to_string() }
+    fn side(&self) -> String { utils::v2_side_str(self.side_raw() as u8).to_string() }
+    fn side_raw(&self) -> u32 { self.side.to_u64() as u32 }
+    fn builder(&self) -> &[u8] { &self.builder }
+    fn metadata(&self) -> &[u8] { &self.metadata }
+}
+
+trait V2OrdersMatched {
+    fn taker_order_hash(&self) -> &[u8];
+    fn taker_order_maker(&self) -> &[u8];
+    fn side(&self) -> u8;
+    fn token_id(&self) -> &substreams::scalar::BigInt;
+    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt;
+    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt;
+}
+
+impl V2OrdersMatched for abi::ctf_exchange_v2::events::OrdersMatched {
+    fn taker_order_hash(&self) -> &[u8] { &self.taker_order_hash }
+    fn taker_order_maker(&self) -> &[u8] { &self.taker_order_maker }
+    fn side(&self) -> u8 { self.side.to_u64() as u8 }
+    fn token_id(&self) -> &substreams::scalar::BigInt { &self.token_id }
+    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.maker_amount_filled }
+    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.taker_amount_filled }
+}
+
+impl V2OrdersMatched for abi::neg_risk_exchange_v2::events::OrdersMatched {
+    fn taker_order_hash(&self) -> &[u8] { &self.taker_order_hash }
+    fn taker_order_maker(&self) -> &[u8] { &self.taker_order_maker }
+    fn side(&self) -> u8 { self.side.to_u64() as u8 }
+    fn token_id(&self) -> &substreams::scalar::BigInt { &self.token_id }
+    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.maker_amount_filled }
+    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.taker_amount_filled }
+}
+
+/// Transaction-level context copied onto every extracted event, used to attribute
+/// fills to Polymarket's operator relayers vs. direct contract callers.
+struct TxContext {
+    tx_from: String,
+    tx_to: String,
+    tx_index: u32,
+    gas_used: u64,
+    effective_gas_price: String,
+    call_address: String,
+}
+
+impl TxContext {
+    fn new(trx: &eth::TransactionTrace, call: Option<&eth::Call>) -> Self {
+        TxContext {
+            tx_from: Hex::encode(&trx.from),
+            tx_to: Hex::encode(&trx.to),
+            tx_index: trx.index,
+            gas_used: trx.gas_used,
+            // Firehose reports the effective price in `gas_price` for EIP-1559 transactions
+            effective_gas_price: trx
+                .gas_price
+                .as_ref()
+                .map(|p| substreams::scalar::BigInt::from_unsigned_bytes_be(&p.bytes).to_string())
+                .unwrap_or_else(|| "0".to_string()),
+            call_address: Hex::encode(call.map_or(&trx.to, |call| &call.address)),
+        }
+    }
+}
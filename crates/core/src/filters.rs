@@ -0,0 +1,74 @@
+use bigdecimal::BigDecimal;
+use std::str::FromStr;
+
+use crate::pb::polymarket::orderbook::v1::OrderFilledEvent;
+use crate::utils;
+
+/// Fill filter parsed from module params.
+///
+/// Params are whitespace-separated `key:value` terms, e.g.
+/// `token_id:1234 trader:0xabc... version:v2 exchange:neg_risk min_collateral:1000000`.
+/// Repeating a key ORs its values; different keys are ANDed. Empty params match everything.
+#[derive(Debug, Default)]
+pub struct FillFilter {
+    token_ids: Vec<String>,
+    traders: Vec<String>,
+    versions: Vec<String>,
+    exchanges: Vec<String>,
+    min_collateral: Option<BigDecimal>,
+}
+
+impl FillFilter {
+    pub fn parse(params: &str) -> Result<Self, substreams::errors::Error> {
+        let mut filter = FillFilter::default();
+
+        for term in params.split_whitespace() {
+            let (key, value) = term
+                .split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("invalid filter term {:?}, expected key:value", term))?;
+
+            match key {
+                "token_id" => filter.token_ids.push(value.to_string()),
+                "trader" => filter.traders.push(utils::normalize_address(value)),
+                "version" => match value {
+                    "v1" | "v2" => filter.versions.push(value.to_string()),
+                    _ => return Err(anyhow::anyhow!("invalid version {:?}, expected v1 or v2", value)),
+                },
+                "exchange" => match value {
+                    "ctf" | "neg_risk" => filter.exchanges.push(value.to_string()),
+                    _ => return Err(anyhow::anyhow!("invalid exchange {:?}, expected ctf or neg_risk", value)),
+                },
+                "min_collateral" => {
+                    let min = BigDecimal::from_str(value)
+                        .map_err(|_| anyhow::anyhow!("invalid min_collateral {:?}", value))?;
+                    filter.min_collateral = Some(min);
+                }
+                _ => return Err(anyhow::anyhow!("unknown filter key {:?}", key)),
+            }
+        }
+
+        Ok(filter)
+    }
+
+    pub fn matches(&self, event: &OrderFilledEvent) -> bool {
+        if !self.token_ids.is_empty() && !self.token_ids.contains(&event.token_id) {
+            return false;
+        }
+        if !self.traders.is_empty() && !self.traders.iter().any(|t| *t == event.maker || *t == event.taker) {
+            return false;
+        }
+        if !self.versions.is_empty() && !self.versions.contains(&event.exchange_version) {
+            return false;
+        }
+        if !self.exchanges.is_empty() && !self.exchanges.contains(&event.exchange) {
+            return false;
+        }
+        if let Some(min) = &self.min_collateral {
+            let collateral = BigDecimal::from_str(utils::collateral_amount(event)).unwrap_or_default();
+            if collateral < *min {
+                return false;
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,115 @@
+use bigdecimal::BigDecimal;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use substreams::scalar::BigInt;
+
+use crate::pb::polymarket::orderbook::v1::{OrderFilledEvents, PriceLevel};
+use crate::utils;
+
+/// Maker fills are bucketed into windows of this length; a ladder sums the current and previous window
+pub const LADDER_WINDOW_SECONDS: u64 = 3600;
+
+/// Fill prices are rounded to ticks of 1/`TICKS_PER_UNIT` collateral, Polymarket's default tick size
+pub const TICKS_PER_UNIT: u64 = 100;
+
+/// Per-level counters kept for every window: `size` (raw outcome tokens) and `fills`
+pub const LEVEL_FIELDS: [&str; 2] = ["size", "fills"];
+
+/// Window a block timestamp falls in
+pub fn window_of(timestamp_seconds: u64) -> u64 {
+    timestamp_seconds / LADDER_WINDOW_SECONDS
+}
+
+/// Key prefix of every level of `window`, for pruning windows no ladder reads
+pub fn window_prefix(window: u64) -> String {
+    format!("{}:", window)
+}
+
+/// `side` is `bid` or `ask`, `tick` the price in ticks and `field` one of `LEVEL_FIELDS`
+pub fn level_key(window: u64, token_id: &str, side: &str, tick: u64, field: &str) -> String {
+    format!("{}:{}:{}:{}:{}", window, token_id, side, tick, field)
+}
+
+/// Side of the book the maker's resting order was on: a maker paying collateral was bidding,
+/// a maker paying outcome tokens was asking
+pub fn maker_side(maker_asset_id: &str) -> &'static str {
+    if maker_asset_id == "0" {
+        "bid"
+    } else {
+        "ask"
+    }
+}
+
+/// Nearest tick of `price`, clamped to the tradable range of one tick to one tick below 1
+pub fn tick_of(price: &BigDecimal) -> u64 {
+    let ticks = (price * BigDecimal::from(TICKS_PER_UNIT)).round(0);
+    ticks.to_string().parse::<u64>().unwrap_or(0).clamp(1, TICKS_PER_UNIT - 1)
+}
+
+/// Maker size and fill count per `(token_id, side, tick)` of one block's fills. When orders are
+/// matched, the taker order is also filled against the exchange contract; that fill did not rest
+/// on the book and is skipped.
+pub fn block_levels(fills: &OrderFilledEvents) -> BTreeMap<(String, &'static str, u64), (BigInt, u64)> {
+    let mut levels: BTreeMap<(String, &'static str, u64), (BigInt, u64)> = BTreeMap::new();
+
+    for fill in fills.events.iter().filter(|f| f.taker != f.call_address) {
+        let key = (fill.token_id.clone(), maker_side(&fill.maker_asset_id), tick_of(&utils::outcome_price(fill)));
+        let level = levels.entry(key).or_insert_with(|| (BigInt::zero(), 0));
+        level.0 = level.0.clone() + BigInt::from_str(utils::token_amount(fill)).unwrap_or_else(|_| BigInt::zero());
+        level.1 += 1;
+    }
+
+    levels
+}
+
+/// Bid levels (best first, descending) and ask levels (best first, ascending) of `token_id` over
+/// the window of `now` and the one before, read through `counter(level_key(..))`
+pub fn ladder(token_id: &str, now: u64, counter: impl Fn(&str) -> Option<BigInt>) -> (Vec<PriceLevel>, Vec<PriceLevel>) {
+    let window = window_of(now);
+    let side_levels = |side: &str| {
+        let mut levels = vec![];
+        for tick in 1..TICKS_PER_UNIT {
+            let sum = |field: &str| {
+                [window.saturating_sub(1), window]
+                    .iter()
+                    .filter_map(|w| counter(&level_key(*w, token_id, side, tick, field)))
+                    .fold(BigInt::zero(), |total, value| total + value)
+            };
+            let fills = sum("fills").to_u64();
+            if fills == 0 {
+                continue;
+            }
+            levels.push(PriceLevel {
+                price: tick_price(tick).to_string(),
+                size: sum("size").to_string(),
+                order_count: fills,
+            });
+        }
+        levels
+    };
+
+    let mut bids = side_levels("bid");
+    bids.reverse();
+    (bids, side_levels("ask"))
+}
+
+/// Estimated spread (best ask − best bid, 0 if either side is empty or the levels cross) and
+/// depth (raw collateral value of every level) of a ladder
+pub fn spread_and_depth(bids: &[PriceLevel], asks: &[PriceLevel]) -> (BigDecimal, BigDecimal) {
+    let price = |level: &PriceLevel| BigDecimal::from_str(&level.price).unwrap_or_default();
+    let spread = match (bids.first(), asks.first()) {
+        (Some(bid), Some(ask)) if price(ask) > price(bid) => price(ask) - price(bid),
+        _ => BigDecimal::from(0),
+    };
+    let depth = bids
+        .iter()
+        .chain(asks)
+        .map(|level| price(level) * BigDecimal::from_str(&level.size).unwrap_or_default())
+        .sum();
+
+    (spread, depth)
+}
+
+fn tick_price(tick: u64) -> BigDecimal {
+    (BigDecimal::from(tick) / BigDecimal::from(TICKS_PER_UNIT)).normalized()
+}
@@ -0,0 +1,27 @@
+//! Decoding and normalization of Polymarket CTF and Neg Risk exchange events (CLOB v1 and v2)
+//! into the `polymarket.orderbook.v1` protos. Free of substreams handlers and stores, so it
+//! builds for native targets as well as for the wasm modules in `polymarket-orderbook-substreams`.
+
+pub mod abi;
+pub mod admin_events;
+pub mod alerts;
+pub mod builders;
+pub mod conditions;
+pub mod consistency;
+pub mod filters;
+pub mod ladder;
+pub mod metadata;
+pub mod neg_risk;
+pub mod registry;
+pub mod twap;
+pub mod utils;
+pub mod volatility;
+pub mod wash;
+
+mod decode;
+
+#[path = "pb/mod.rs"]
+#[allow(dead_code)]
+pub mod pb;
+
+pub use decode::{exchange_index_keys, extract_exchange_events, ExchangeFill};
@@ -0,0 +1,172 @@
+use std::collections::BTreeMap;
+use substreams::Hex;
+
+use crate::pb::polymarket::orderbook::v1::{EventParam, OrderFilledEvent, OrderFilledEvents};
+
+/// Layout name of metadata no layout recognizes
+pub const UNKNOWN_LAYOUT: &str = "unknown";
+
+/// A known arrangement of the v2 `metadata` bytes32. Layouts are tried in order and the first
+/// that decodes names the layout of a fill.
+pub trait MetadataLayout {
+    fn name(&self) -> &'static str;
+    /// Decoded fields, or None if `metadata` is not in this layout
+    fn decode(&self, metadata: &[u8; 32]) -> Option<Vec<EventParam>>;
+}
+
+/// All zeros: the order carried no metadata
+pub struct Empty;
+
+impl MetadataLayout for Empty {
+    fn name(&self) -> &'static str {
+        "empty"
+    }
+
+    fn decode(&self, metadata: &[u8; 32]) -> Option<Vec<EventParam>> {
+        metadata.iter().all(|b| *b == 0).then(Vec::new)
+    }
+}
+
+/// A printable UTF-8 string, left-aligned and zero-padded, like Solidity `bytes32("tag")`
+pub struct Utf8Tag;
+
+impl MetadataLayout for Utf8Tag {
+    fn name(&self) -> &'static str {
+        "utf8_tag"
+    }
+
+    fn decode(&self, metadata: &[u8; 32]) -> Option<Vec<EventParam>> {
+        let length = metadata.iter().position(|b| *b == 0).unwrap_or(32);
+        if length == 0 || metadata[length..].iter().any(|b| *b != 0) {
+            return None;
+        }
+        let tag = std::str::from_utf8(&metadata[..length]).ok()?;
+        if tag.chars().any(|c| c.is_control()) {
+            return None;
+        }
+        Some(vec![param("tag", tag)])
+    }
+}
+
+/// A small integer, right-aligned like a `uint256`: a numeric client or app identifier
+pub struct AppId;
+
+impl MetadataLayout for AppId {
+    fn name(&self) -> &'static str {
+        "app_id"
+    }
+
+    fn decode(&self, metadata: &[u8; 32]) -> Option<Vec<EventParam>> {
+        if metadata[..24].iter().any(|b| *b != 0) {
+            return None;
+        }
+        let id = metadata[24..].iter().fold(0u64, |id, b| (id << 8) | *b as u64);
+        Some(vec![param("app_id", &id.to_string())])
+    }
+}
+
+/// An address, right-aligned like an ABI-encoded `address`
+pub struct Address;
+
+impl MetadataLayout for Address {
+    fn name(&self) -> &'static str {
+        "address"
+    }
+
+    fn decode(&self, metadata: &[u8; 32]) -> Option<Vec<EventParam>> {
+        if metadata[..12].iter().any(|b| *b != 0) {
+            return None;
+        }
+        Some(vec![param("address", &Hex::encode(&metadata[12..]))])
+    }
+}
+
+/// Decodes metadata with an ordered list of layouts.
+///
+/// The default decoder tries `empty`, `utf8_tag`, `app_id` and `address`, in that order, so
+/// a right-aligned value that fits in 8 bytes reads as an app ID, not an address.
+/// `with_layout` puts a layout ahead of the existing ones.
+pub struct MetadataDecoder {
+    layouts: Vec<Box<dyn MetadataLayout>>,
+}
+
+impl Default for MetadataDecoder {
+    fn default() -> Self {
+        MetadataDecoder {
+            layouts: vec![Box::new(Empty), Box::new(Utf8Tag), Box::new(AppId), Box::new(Address)],
+        }
+    }
+}
+
+impl MetadataDecoder {
+    pub fn new(layouts: Vec<Box<dyn MetadataLayout>>) -> Self {
+        MetadataDecoder { layouts }
+    }
+
+    pub fn with_layout(mut self, layout: impl MetadataLayout + 'static) -> Self {
+        self.layouts.insert(0, Box::new(layout));
+        self
+    }
+
+    /// Layout name and decoded fields of raw metadata; `unknown` with no fields if no layout
+    /// matches or the metadata is not 32 bytes
+    pub fn decode(&self, metadata: &[u8]) -> (String, Vec<EventParam>) {
+        let Ok(metadata) = <&[u8; 32]>::try_from(metadata) else {
+            return (UNKNOWN_LAYOUT.to_string(), vec![]);
+        };
+
+        for layout in &self.layouts {
+            if let Some(fields) = layout.decode(metadata) {
+                return (layout.name().to_string(), fields);
+            }
+        }
+        (UNKNOWN_LAYOUT.to_string(), vec![])
+    }
+
+    /// Sets `metadata_layout` and `metadata_fields` of a v2 fill from its hex `metadata`.
+    /// v1 fills, which carry no metadata, are left untouched.
+    pub fn apply(&self, fill: &mut OrderFilledEvent) {
+        if fill.metadata.is_empty() {
+            return;
+        }
+        let raw = Hex::decode(&fill.metadata).unwrap_or_default();
+        let (layout, fields) = self.decode(&raw);
+        fill.metadata_layout = layout;
+        fill.metadata_fields = fields;
+    }
+}
+
+/// Key of the `store_metadata_layouts` fill count of a layout
+pub fn layout_key(layout: &str) -> String {
+    format!("layout:{}", layout)
+}
+
+/// Key of the `store_metadata_layouts` count of a builder's fills with unknown metadata
+pub fn unknown_key(builder: &str) -> String {
+    format!("unknown:{}", builder)
+}
+
+/// Decoded v2 fills of one block per layout, and unknown-layout fills per builder code with the
+/// last unknown metadata seen
+pub fn block_layouts(fills: &OrderFilledEvents) -> (BTreeMap<String, u64>, BTreeMap<String, (u64, String)>) {
+    let mut layouts: BTreeMap<String, u64> = BTreeMap::new();
+    let mut unknown: BTreeMap<String, (u64, String)> = BTreeMap::new();
+
+    for fill in fills.events.iter().filter(|f| !f.metadata_layout.is_empty()) {
+        *layouts.entry(fill.metadata_layout.clone()).or_default() += 1;
+        if fill.metadata_layout == UNKNOWN_LAYOUT {
+            let entry = unknown.entry(fill.builder.clone()).or_default();
+            entry.0 += 1;
+            entry.1 = fill.metadata.clone();
+        }
+    }
+
+    (layouts, unknown)
+}
+
+fn param(name: &str, value: &str) -> EventParam {
+    EventParam {
+        name: name.to_string(),
+        value: value.to_string(),
+    }
+}
@@ -0,0 +1,338 @@
+use bigdecimal::{BigDecimal, Signed};
+use num_bigint::BigUint;
+use std::str::FromStr;
+use prost_types::Timestamp;
+use substreams::Hex;
+use substreams_ethereum::pb::eth::v2 as eth;
+use substreams_ethereum::Event;
+use tiny_keccak::{Hasher, Keccak};
+
+use crate::pb::polymarket::orderbook::v1::{
+    ExchangeEvents, NegRiskQuestion, NegRiskQuestions, OrderFilledEvent, OutcomePrice, RegisteredToken, TradingOpportunity,
+};
+use crate::registry::{ContractRegistry, ExchangeContract};
+use crate::{abi, utils};
+
+/// NegRiskAdapter on Polygon mainnet; prepares the conditions traded on the Neg Risk exchanges
+pub const POLYGON_NEG_RISK_ADAPTER: &str = "d91e80cf2e7be2e162c6513ced06f1dd0da35296";
+
+/// Field modulus of alt_bn128, the curve `CTHelpers.getCollectionId` hashes collections onto
+const ALT_BN128_P: &[u8] = b"21888242871839275222246405745257275088696311157297823662689037894645226208583";
+
+/// Adapter addresses from module params: whitespace- or comma-separated `0x` addresses.
+/// Empty params mean the Polygon mainnet adapter.
+pub fn adapters_from_params(params: &str) -> Result<Vec<String>, substreams::errors::Error> {
+    let mut adapters = vec![];
+    for entry in params.split(|c: char| c.is_whitespace() || c == ',').filter(|e| !e.is_empty()) {
+        let address = utils::normalize_address(entry);
+        if address.len() != 40 || !address.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(anyhow::anyhow!("invalid adapter address {:?}", entry));
+        }
+        adapters.push(address);
+    }
+
+    if adapters.is_empty() {
+        adapters.push(POLYGON_NEG_RISK_ADAPTER.to_string());
+    }
+    Ok(adapters)
+}
+
+/// CTF condition ID of a binary question prepared by `oracle`:
+/// `keccak256(oracle ++ question_id ++ uint256(2))`, as `ConditionalTokens.getConditionId` computes it
+pub fn condition_id(oracle: &[u8], question_id: &[u8]) -> String {
+    Hex::encode(keccak256(&[oracle, question_id, &uint256(2)]))
+}
+
+/// CTF position ID (the ERC-1155 token ID) of one outcome of a top-level binary condition,
+/// `keccak256(collateral ++ getCollectionId(0, condition_id, index_set))` as `CTHelpers` computes it.
+/// Index set 1 is the YES outcome, 2 the NO outcome.
+pub fn position_id(collateral: &[u8], condition_id: &[u8], index_set: u8) -> String {
+    let collection_id = collection_id(condition_id, index_set);
+    BigUint::from_bytes_be(&keccak256(&[collateral, &collection_id])).to_string()
+}
+
+/// `CTHelpers.getCollectionId` with no parent collection: the hash of the condition and index set
+/// is lifted onto alt_bn128 and the point compressed into its x coordinate, with bit 254 set for
+/// an odd y. Hash values with no point are incremented until one is found.
+fn collection_id(condition_id: &[u8], index_set: u8) -> [u8; 32] {
+    let p = BigUint::parse_bytes(ALT_BN128_P, 10).unwrap();
+    let hash = keccak256(&[condition_id, &uint256(index_set)]);
+    let odd = hash[0] & 0x80 != 0;
+
+    let mut x = BigUint::from_bytes_be(&hash);
+    loop {
+        x = (x + 1u32) % &p;
+        let yy = (&x * &x * &x + 3u32) % &p;
+        let y = yy.modpow(&((&p + 1u32) >> 2), &p);
+        if (&y * &y) % &p == yy {
+            break;
+        }
+    }
+    // The contract negates y until its parity matches `odd`, so the compression flag is `odd`
+    x.set_bit(254, odd);
+
+    let bytes = x.to_bytes_be();
+    let mut collection_id = [0u8; 32];
+    collection_id[32 - bytes.len()..].copy_from_slice(&bytes);
+    collection_id
+}
+
+fn keccak256(parts: &[&[u8]]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    for part in parts {
+        hasher.update(part);
+    }
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+fn uint256(value: u8) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[31] = value;
+    word
+}
+
+/// QuestionPrepared events emitted by any of `adapters` in successful transactions, sorted by ordinal
+pub fn extract_neg_risk_questions(adapters: &[String], blk: &eth::Block) -> NegRiskQuestions {
+    let mut questions = vec![];
+
+    for view in blk.logs() {
+        let (trx, log) = (view.receipt.transaction, view.log);
+        let adapter = Hex::encode(&log.address);
+        if !adapters.contains(&adapter) {
+            continue;
+        }
+        let Some(event) = abi::neg_risk_adapter::events::QuestionPrepared::match_and_decode(log) else {
+            continue;
+        };
+
+        questions.push(NegRiskQuestion {
+            market_id: Hex::encode(event.market_id),
+            question_id: Hex::encode(event.question_id),
+            index: event.index.to_u64(),
+            condition_id: condition_id(&log.address, &event.question_id),
+            adapter,
+            transaction_hash: Hex::encode(&trx.hash),
+            block_number: blk.number,
+            ordinal: log.ordinal,
+        });
+    }
+    questions.sort_by_key(|q| q.ordinal);
+
+    NegRiskQuestions {
+        questions,
+        block_number: blk.number,
+        block_hash: Hex::encode(&blk.hash),
+        timestamp: Some(Timestamp {
+            seconds: blk.timestamp_seconds() as i64,
+            nanos: 0,
+        }),
+    }
+}
+
+/// Tokens registered by `TokenRegistered` admin events, and the YES and NO tokens of every
+/// condition a `QuestionPrepared` created.
+///
+/// `registerToken(token, complement, conditionId)` emits `(token, complement)` then
+/// `(complement, token)`. Whether `token0` is YES or NO is read off the condition's CTF position IDs
+/// over the exchange's collateral in `registry`, so the order of the events does not matter.
+/// Registrations that are neither position are skipped. v2 exchanges emit no `TokenRegistered`, so
+/// neg-risk questions are what make their tokens known: each gets its positions over the collateral
+/// of each Neg Risk exchange collateral in `registry`.
+pub fn registered_tokens(
+    registry: &ContractRegistry,
+    events: &ExchangeEvents,
+    questions: &NegRiskQuestions,
+) -> Vec<RegisteredToken> {
+    let mut tokens = vec![];
+
+    for event in events.admin_events.iter().filter(|e| e.event_type == "TokenRegistered") {
+        let param = |name: &str| {
+            event
+                .params
+                .iter()
+                .find(|p| p.name == name)
+                .map(|p| p.value.clone())
+                .unwrap_or_default()
+        };
+        let (token0, token1, condition) = (param("token0"), param("token1"), param("condition_id"));
+        let Some(collateral) = registry.collateral(&event.exchange, &event.exchange_version) else {
+            continue;
+        };
+        let Some(outcome) = outcome_of(collateral, &condition, &token0) else {
+            continue;
+        };
+
+        tokens.push(RegisteredToken {
+            outcome: outcome.to_string(),
+            token_id: token0,
+            complement_token_id: token1,
+            condition_id: condition,
+            exchange: event.exchange.clone(),
+            exchange_version: event.exchange_version.clone(),
+            block_number: event.block_number,
+            ordinal: event.ordinal,
+        });
+    }
+
+    let exchanges = neg_risk_exchanges(registry, questions.block_number);
+    for question in &questions.questions {
+        let Ok(condition) = Hex::decode(&question.condition_id) else {
+            continue;
+        };
+        for exchange in &exchanges {
+            let Ok(collateral) = Hex::decode(&exchange.collateral) else {
+                continue;
+            };
+            let yes = position_id(&collateral, &condition, 1);
+            let no = position_id(&collateral, &condition, 2);
+
+            for (outcome, token_id, complement_token_id) in [("yes", &yes, &no), ("no", &no, &yes)] {
+                tokens.push(RegisteredToken {
+                    outcome: outcome.to_string(),
+                    token_id: token_id.clone(),
+                    complement_token_id: complement_token_id.clone(),
+                    condition_id: question.condition_id.clone(),
+                    exchange: exchange.kind.clone(),
+                    exchange_version: exchange.version.clone(),
+                    block_number: question.block_number,
+                    ordinal: question.ordinal,
+                });
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Neg Risk exchanges of `registry`, one per collateral: the latest to have started by `block_number`
+fn neg_risk_exchanges(registry: &ContractRegistry, block_number: u64) -> Vec<&ExchangeContract> {
+    let mut exchanges: Vec<&ExchangeContract> = vec![];
+    for exchange in registry.exchanges.iter().filter(|e| e.kind == "neg_risk") {
+        match exchanges.iter_mut().find(|e| e.collateral == exchange.collateral) {
+            Some(current) if exchange.start_block <= block_number && exchange.start_block > current.start_block => {
+                *current = exchange;
+            }
+            Some(_) => {}
+            None => exchanges.push(exchange),
+        }
+    }
+    exchanges
+}
+
+/// `"yes"` or `"no"` when `token_id` is that position of `condition_id` over `collateral`
+fn outcome_of(collateral: &str, condition_id: &str, token_id: &str) -> Option<&'static str> {
+    let collateral = Hex::decode(collateral).ok()?;
+    let condition = Hex::decode(condition_id).ok()?;
+
+    [("yes", 1), ("no", 2)]
+        .into_iter()
+        .find(|(_, index_set)| position_id(&collateral, &condition, *index_set) == token_id)
+        .map(|(outcome, _)| outcome)
+}
+
+/// YES price implied by a fill: the outcome price on a YES token, its complement on a NO token
+pub fn yes_price(fill: &OrderFilledEvent, token: &RegisteredToken) -> BigDecimal {
+    let price = utils::outcome_price(fill);
+    if token.outcome == "no" {
+        BigDecimal::from(1) - price
+    } else {
+        price
+    }
+}
+
+/// Arbitrage thresholds parsed from module params.
+///
+/// Params are whitespace-separated `key:value` terms, e.g. `min_deviation:0.02 max_age_blocks:1800`.
+#[derive(Debug, Clone)]
+pub struct ArbitrageParams {
+    /// Smallest |sum of YES prices − 1| reported
+    pub min_deviation: BigDecimal,
+    /// Outcome prices older than this many blocks lower the confidence
+    pub max_age_blocks: u64,
+}
+
+impl Default for ArbitrageParams {
+    fn default() -> Self {
+        ArbitrageParams {
+            min_deviation: BigDecimal::from_str("0.02").unwrap(),
+            max_age_blocks: 1800,
+        }
+    }
+}
+
+impl ArbitrageParams {
+    pub fn parse(params: &str) -> Result<Self, substreams::errors::Error> {
+        let mut parsed = ArbitrageParams::default();
+
+        for term in params.split_whitespace() {
+            let (key, value) = term
+                .split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("invalid arbitrage term {:?}, expected key:value", term))?;
+
+            match key {
+                "min_deviation" => {
+                    parsed.min_deviation = BigDecimal::from_str(value)
+                        .ok()
+                        .filter(|v| !v.is_negative())
+                        .ok_or_else(|| anyhow::anyhow!("invalid min_deviation {:?}", value))?;
+                }
+                "max_age_blocks" => {
+                    parsed.max_age_blocks = value
+                        .parse::<u64>()
+                        .map_err(|_| anyhow::anyhow!("invalid max_age_blocks {:?}", value))?;
+                }
+                _ => return Err(anyhow::anyhow!("unknown arbitrage key {:?}", key)),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Opportunity for a neg-risk market whose outcomes are all priced and whose YES prices sum
+/// at least `min_deviation` away from 1. `outcomes` holds the last price of each question index.
+pub fn arbitrage_opportunity(
+    params: &ArbitrageParams,
+    market_id: &str,
+    outcomes: Vec<Option<OutcomePrice>>,
+    block_number: u64,
+    timestamp: Option<Timestamp>,
+) -> Option<TradingOpportunity> {
+    let outcome_count = outcomes.len() as u64;
+    let outcomes: Vec<OutcomePrice> = outcomes.into_iter().collect::<Option<_>>()?;
+    if outcomes.len() < 2 {
+        return None;
+    }
+
+    let sum: BigDecimal = outcomes
+        .iter()
+        .map(|o| BigDecimal::from_str(&o.yes_price).unwrap_or_default())
+        .sum();
+    let difference = &sum - BigDecimal::from(1);
+    if difference.abs() < params.min_deviation {
+        return None;
+    }
+
+    let fresh = outcomes
+        .iter()
+        .filter(|o| block_number.saturating_sub(o.block_number) <= params.max_age_blocks)
+        .count();
+    let confidence = BigDecimal::from(fresh as u64) / BigDecimal::from(outcome_count);
+
+    Some(TradingOpportunity {
+        market_a: market_id.to_string(),
+        market_b: String::new(),
+        price_difference: difference.to_string(),
+        potential_profit: difference.abs().to_string(),
+        confidence: confidence.round(4).to_string(),
+        expires_at: None,
+        strategy: if difference.is_negative() { "buy_yes_set" } else { "buy_no_set" }.to_string(),
+        yes_price_sum: sum.to_string(),
+        outcome_count,
+        outcomes,
+        block_number,
+        timestamp,
+    })
+}
@@ -0,0 +1,132 @@
+use crate::utils;
+
+/// A Polymarket exchange deployment
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExchangeContract {
+    /// Lowercase hex, no `0x` prefix (matches `Hex::encode`)
+    pub address: String,
+    /// `"ctf"` | `"neg_risk"`
+    pub kind: String,
+    /// `"v1"` | `"v2"`
+    pub version: String,
+    /// Collateral the exchange's CTF positions are backed by, lowercase hex without `0x`;
+    /// token IDs are derived from it (see `neg_risk::position_id`)
+    pub collateral: String,
+    /// Logs emitted before this block are ignored
+    pub start_block: u64,
+}
+
+/// Exchange contracts indexed by the package, overridable via module params.
+///
+/// Params are whitespace- or comma-separated entries of the form
+/// `kind:version:0xaddress:0xcollateral[:start_block]`, e.g. `ctf:v2:0xE111...996B:0x2791...4174:84902353`.
+/// The `polygon` preset expands to the Polygon mainnet deployments; empty params mean `polygon`.
+/// Listing entries without the preset replaces the defaults entirely (e.g. for Amoy testnet).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContractRegistry {
+    pub exchanges: Vec<ExchangeContract>,
+}
+
+impl Default for ContractRegistry {
+    fn default() -> Self {
+        ContractRegistry { exchanges: polygon_exchanges() }
+    }
+}
+
+impl ContractRegistry {
+    pub fn from_params(params: &str) -> Result<Self, substreams::errors::Error> {
+        if params.trim().is_empty() {
+            return Ok(ContractRegistry::default());
+        }
+
+        let mut exchanges = vec![];
+        for entry in params.split(|c: char| c.is_whitespace() || c == ',').filter(|e| !e.is_empty()) {
+            if entry == "polygon" {
+                exchanges.extend(polygon_exchanges());
+                continue;
+            }
+
+            let parts: Vec<&str> = entry.split(':').collect();
+            if parts.len() < 4 || parts.len() > 5 {
+                return Err(anyhow::anyhow!(
+                    "invalid registry entry {:?}, expected kind:version:0xaddress:0xcollateral[:start_block]",
+                    entry
+                ));
+            }
+            let kind = match parts[0] {
+                "ctf" | "neg_risk" => parts[0],
+                other => return Err(anyhow::anyhow!("invalid exchange kind {:?}, expected ctf or neg_risk", other)),
+            };
+            let version = match parts[1] {
+                "v1" | "v2" => parts[1],
+                other => return Err(anyhow::anyhow!("invalid exchange version {:?}, expected v1 or v2", other)),
+            };
+            let address = parse_address(parts[2]).ok_or_else(|| anyhow::anyhow!("invalid exchange address {:?}", parts[2]))?;
+            let collateral =
+                parse_address(parts[3]).ok_or_else(|| anyhow::anyhow!("invalid collateral address {:?}", parts[3]))?;
+            let start_block = match parts.get(4) {
+                Some(block) => block
+                    .parse::<u64>()
+                    .map_err(|_| anyhow::anyhow!("invalid start block {:?}", block))?,
+                None => 0,
+            };
+
+            exchanges.push(ExchangeContract {
+                address,
+                kind: kind.to_string(),
+                version: version.to_string(),
+                collateral,
+                start_block,
+            });
+        }
+
+        Ok(ContractRegistry { exchanges })
+    }
+
+    /// Registry entry for a log emitted by `address` at `block_number`
+    pub fn find(&self, address: &[u8], block_number: u64) -> Option<&ExchangeContract> {
+        let address = substreams::Hex::encode(address);
+        self.exchanges
+            .iter()
+            .find(|e| e.address == address && block_number >= e.start_block)
+    }
+
+    /// Collateral of the first `kind`/`version` exchange, the one its token IDs are derived from
+    pub fn collateral(&self, kind: &str, version: &str) -> Option<&str> {
+        self.exchanges
+            .iter()
+            .find(|e| e.kind == kind && e.version == version)
+            .map(|e| e.collateral.as_str())
+    }
+}
+
+/// Lowercase hex of a `0x` address, `None` unless it is 20 bytes
+fn parse_address(value: &str) -> Option<String> {
+    let address = utils::normalize_address(value);
+    (address.len() == 40 && address.chars().all(|c| c.is_ascii_hexdigit())).then_some(address)
+}
+
+/// USDC.e on Polygon mainnet, the collateral of the positions traded on the CTF exchanges
+pub const POLYGON_USDC: &str = "2791bca1f2de4661ed88a30c99a7a9449aa84174";
+
+/// WrappedCollateral of the Polygon NegRiskAdapter, the collateral of the positions traded on the
+/// Neg Risk exchanges
+pub const POLYGON_NEG_RISK_WRAPPED_COLLATERAL: &str = "3a3bd7bb9528e159577f7c2e685cc81a765002e2";
+
+fn polygon_exchanges() -> Vec<ExchangeContract> {
+    [
+        ("4bfb41d5b3570defd03c39a9a4d8de6bd8b8982e", "ctf", "v1", POLYGON_USDC, 57_000_000),
+        ("c5d563a36ae78145c45a50134d48a1215220f80a", "neg_risk", "v1", POLYGON_NEG_RISK_WRAPPED_COLLATERAL, 57_000_000),
+        ("e111180000d2663c0091e4f400237545b87b996b", "ctf", "v2", POLYGON_USDC, 84_902_353),
+        ("e2222d279d744050d28e00520010520000310f59", "neg_risk", "v2", POLYGON_NEG_RISK_WRAPPED_COLLATERAL, 84_902_353),
+    ]
+    .into_iter()
+    .map(|(address, kind, version, collateral, start_block)| ExchangeContract {
+        address: address.to_string(),
+        kind: kind.to_string(),
+        version: version.to_string(),
+        collateral: collateral.to_string(),
+        start_block,
+    })
+    .collect()
+}
@@ -0,0 +1,120 @@
+use bigdecimal::BigDecimal;
+use std::str::FromStr;
+use substreams::scalar::BigInt;
+
+use crate::pb::polymarket::orderbook::v1::TokenPrice;
+
+/// Each TWAP window is accumulated in this many slots; a TWAP reads the last `SLOTS_PER_WINDOW`
+/// slots, so it covers between 11/12 of the window and the whole window
+pub const SLOTS_PER_WINDOW: u64 = 12;
+
+/// Prices are accumulated as integers scaled by 10^18
+pub const PRICE_DECIMALS: u32 = 18;
+
+/// Rolling windows in seconds, parsed from module params; shared with `volatility`.
+///
+/// Params are whitespace-separated `window:{seconds}` terms, e.g. `window:3600 window:86400`.
+/// Windows must be at least 60 seconds and a multiple of 12. Empty params mean 1h and 24h.
+pub fn windows_from_params(params: &str) -> Result<Vec<u64>, substreams::errors::Error> {
+    let mut windows = vec![];
+    for term in params.split_whitespace() {
+        let value = term
+            .strip_prefix("window:")
+            .ok_or_else(|| anyhow::anyhow!("invalid window term {:?}, expected window:{{seconds}}", term))?;
+        let window = value
+            .parse::<u64>()
+            .ok()
+            .filter(|w| *w >= 60 && w % SLOTS_PER_WINDOW == 0)
+            .ok_or_else(|| anyhow::anyhow!("invalid window {:?}, expected seconds >= 60 and a multiple of 12", value))?;
+        if !windows.contains(&window) {
+            windows.push(window);
+        }
+    }
+
+    if windows.is_empty() {
+        windows = vec![3600, 86400];
+    }
+    Ok(windows)
+}
+
+pub fn slot_of(window: u64, timestamp: u64) -> u64 {
+    timestamp / (window / SLOTS_PER_WINDOW)
+}
+
+/// Accumulator key; `field` is `price_seconds` (scaled price × seconds held) or `seconds`
+pub fn slot_key(window: u64, slot: u64, token_id: &str, field: &str) -> String {
+    format!("{}:{}:{}:{}", window, slot, token_id, field)
+}
+
+/// Key prefix of every accumulator of one slot, for pruning slots no TWAP reads any more
+pub fn slot_prefix(window: u64, slot: u64) -> String {
+    format!("{}:{}:", window, slot)
+}
+
+/// Splits the time a price was held, from `from` to `to` (unix seconds), across the slots of
+/// `window`. Time more than one window before `to` is dropped, since no later TWAP reads it.
+pub fn held_intervals(window: u64, from: u64, to: u64) -> Vec<(u64, u64)> {
+    let slot_length = window / SLOTS_PER_WINDOW;
+    let mut start = from.max(to.saturating_sub(window));
+    let mut intervals = vec![];
+
+    while start < to {
+        let slot = start / slot_length;
+        let end = ((slot + 1) * slot_length).min(to);
+        intervals.push((slot, end - start));
+        start = end;
+    }
+
+    intervals
+}
+
+/// A price as a 10^18-scaled integer, truncated
+pub fn scale_price(price: &str) -> BigInt {
+    let scaled = BigDecimal::from_str(price).unwrap_or_default() * BigDecimal::from(10u64.pow(PRICE_DECIMALS));
+    BigInt::from_str(&scaled.with_scale(0).to_string()).unwrap_or_else(|_| BigInt::zero())
+}
+
+/// Time-weighted average price of `token_id` over `window` ending at `now`, from the slot
+/// accumulators read through `accumulator(slot_key(..))` plus the time `last` has been held since
+/// its fill. Returns the TWAP and the seconds it covers, or None if the token has no price in the window.
+pub fn twap(
+    window: u64,
+    now: u64,
+    token_id: &str,
+    last: Option<&TokenPrice>,
+    accumulator: impl Fn(&str) -> Option<BigInt>,
+) -> Option<(BigDecimal, u64)> {
+    let current = slot_of(window, now);
+    let first = current.saturating_sub(SLOTS_PER_WINDOW - 1);
+
+    let mut price_seconds = BigDecimal::from(0);
+    let mut seconds = 0u64;
+    for slot in first..=current {
+        price_seconds += decimal(accumulator(&slot_key(window, slot, token_id, "price_seconds")));
+        seconds += accumulator(&slot_key(window, slot, token_id, "seconds")).map(|s| s.to_u64()).unwrap_or(0);
+    }
+
+    // The last price has been held since its fill but is only accumulated when the next fill lands
+    if let Some(last) = last {
+        let since = last.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(now);
+        let price = decimal(Some(scale_price(&last.price)));
+        for (slot, held) in held_intervals(window, since, now) {
+            if slot >= first {
+                price_seconds += price.clone() * BigDecimal::from(held);
+                seconds += held;
+            }
+        }
+    }
+
+    if seconds == 0 {
+        return None;
+    }
+    let average = price_seconds / BigDecimal::from(seconds) / BigDecimal::from(10u64.pow(PRICE_DECIMALS));
+    Some((average, seconds))
+}
+
+fn decimal(value: Option<BigInt>) -> BigDecimal {
+    value
+        .and_then(|v| BigDecimal::from_str(&v.to_string()).ok())
+        .unwrap_or_default()
+}
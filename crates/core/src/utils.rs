@@ -1,7 +1,9 @@
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, Zero};
 use std::str::FromStr;
 use substreams::scalar::BigInt;
 
+use crate::pb::polymarket::orderbook::v1::OrderFilledEvent;
+
 /// Calculate price from maker and taker amounts
 pub fn calculate_price(maker_amount: &BigInt, taker_amount: &BigInt) -> BigDecimal {
     if taker_amount.to_string() == "0" {
@@ -30,8 +32,8 @@ pub fn determine_trade_side(
     let taker_id_str = taker_asset_id.to_string();
     
     // Check if the last digit is even (0,2,4,6,8) or odd (1,3,5,7,9)
-    let maker_is_even = maker_id_str.chars().last().map_or(false, |c| matches!(c, '0'|'2'|'4'|'6'|'8'));
-    let taker_is_even = taker_id_str.chars().last().map_or(false, |c| matches!(c, '0'|'2'|'4'|'6'|'8'));
+    let maker_is_even = maker_id_str.chars().last().is_some_and(|c| matches!(c, '0'|'2'|'4'|'6'|'8'));
+    let taker_is_even = taker_id_str.chars().last().is_some_and(|c| matches!(c, '0'|'2'|'4'|'6'|'8'));
     
     // USDC collateral typically has even asset IDs (divisible by 2)
     // Outcome tokens typically have odd asset IDs
@@ -49,7 +51,7 @@ pub fn generate_order_id(tx_hash: &str, order_hash: &str) -> String {
 
 /// Extract condition ID from asset ID (Polymarket specific logic)
 pub fn extract_condition_id(asset_id: &BigInt) -> String {
-    format!("condition_{}", asset_id.to_string())
+    format!("condition_{}", asset_id)
 }
 
 /// Extract condition ID from asset ID string
@@ -57,11 +59,42 @@ pub fn extract_condition_id_from_str(asset_id: &str) -> String {
     format!("condition_{}", asset_id)
 }
 
+/// Recover the token ID from a market ID built by `extract_condition_id_from_str`
+pub fn token_id_from_market_id(market_id: &str) -> &str {
+    market_id.strip_prefix("condition_").unwrap_or(market_id)
+}
+
 /// Format timestamp for day calculation
 pub fn timestamp_to_day(timestamp: u64) -> u64 {
     timestamp / 86400 // Convert seconds to days
 }
 
+/// Format unix seconds as a UTC `%Y-%m-%d %H:%M:%S` SQL timestamp literal
+pub fn sql_timestamp(seconds: i64) -> String {
+    let (days, secs) = (seconds.div_euclid(86400), seconds.rem_euclid(86400));
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's days_to_civil)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
 /// Pick the non-zero asset ID as the token ID for a v1 OrderFilled event.
 /// V1 always emits collateral (0) on one side and the conditional token ID on the other.
 pub fn v1_token_id(maker_asset_id: &BigInt, taker_asset_id: &BigInt) -> String {
@@ -72,6 +105,15 @@ pub fn v1_token_id(maker_asset_id: &BigInt, taker_asset_id: &BigInt) -> String {
     }
 }
 
+/// Trade side string for a v2 side enum (0=BUY, 1=SELL)
+pub fn v2_side_str(side: u8) -> &'static str {
+    match side {
+        0 => "buy",
+        1 => "sell",
+        _ => "unknown",
+    }
+}
+
 /// Map a v2 (side, token_id) into v1-compatible (maker_asset_id, taker_asset_id).
 /// V2 BUY (side=0): maker pays collateral, receives tokens → maker_asset_id="0", taker_asset_id=token_id
 /// V2 SELL (side=1): maker pays tokens, receives collateral → maker_asset_id=token_id, taker_asset_id="0"
@@ -82,3 +124,38 @@ pub fn v2_assets_from_side(side: u8, token_id: &str) -> (String, String) {
         _ => (token_id.to_string(), "0".to_string()),
     }
 }
+
+/// Lowercase an address and strip any `0x` prefix to match `Hex::encode` output
+pub fn normalize_address(address: &str) -> String {
+    let lower = address.to_lowercase();
+    lower.strip_prefix("0x").unwrap_or(&lower).to_string()
+}
+
+/// Raw collateral amount of a fill: whichever side traded asset ID "0" (collateral)
+pub fn collateral_amount(event: &OrderFilledEvent) -> &str {
+    if event.maker_asset_id == "0" {
+        &event.maker_amount_filled
+    } else {
+        &event.taker_amount_filled
+    }
+}
+
+/// Raw conditional token amount of a fill: the leg opposite the collateral
+pub fn token_amount(event: &OrderFilledEvent) -> &str {
+    if event.maker_asset_id == "0" {
+        &event.taker_amount_filled
+    } else {
+        &event.maker_amount_filled
+    }
+}
+
+/// Collateral paid per outcome token, independent of which side the maker was on
+pub fn outcome_price(event: &OrderFilledEvent) -> BigDecimal {
+    let collateral = BigDecimal::from_str(collateral_amount(event)).unwrap_or_default();
+    let tokens = BigDecimal::from_str(token_amount(event)).unwrap_or_default();
+    if tokens.is_zero() {
+        BigDecimal::zero()
+    } else {
+        collateral / tokens
+    }
+}
@@ -0,0 +1,53 @@
+use bigdecimal::BigDecimal;
+use std::str::FromStr;
+use substreams::scalar::BigInt;
+
+use crate::twap::{slot_key, slot_of, SLOTS_PER_WINDOW};
+
+/// Prices are clamped this far inside (0, 1) before taking log-odds, so a fill at 0 or 1 stays finite
+pub const PRICE_FLOOR: f64 = 0.001;
+
+/// Squared returns are accumulated as integers scaled by 10^18
+pub const RETURN_DECIMALS: u32 = 18;
+
+/// `ln(p / (1 - p))` of a price clamped to `[PRICE_FLOOR, 1 - PRICE_FLOOR]`
+pub fn log_odds(price: &str) -> f64 {
+    let p = price.parse::<f64>().unwrap_or(0.5).clamp(PRICE_FLOOR, 1.0 - PRICE_FLOOR);
+    (p / (1.0 - p)).ln()
+}
+
+/// Squared log-odds return from `old` to `new`, scaled by 10^18 and truncated
+pub fn squared_return(old: &str, new: &str) -> BigInt {
+    let r = log_odds(new) - log_odds(old);
+    let scaled = BigDecimal::try_from(r * r).unwrap_or_default() * BigDecimal::from(10u64.pow(RETURN_DECIMALS));
+    BigInt::from_str(&scaled.with_scale(0).to_string()).unwrap_or_else(|_| BigInt::zero())
+}
+
+/// Realized volatility of `token_id` over `window` ending at `now`: the square root of the sum of
+/// squared log-odds returns between successive fills, read through `accumulator(slot_key(..))`
+/// with fields `squared_returns` and `returns`. Returns it with the number of returns, or None without any.
+pub fn realized_volatility(
+    window: u64,
+    now: u64,
+    token_id: &str,
+    accumulator: impl Fn(&str) -> Option<BigInt>,
+) -> Option<(BigDecimal, u64)> {
+    let current = slot_of(window, now);
+    let first = current.saturating_sub(SLOTS_PER_WINDOW - 1);
+
+    let mut squared_returns = BigInt::zero();
+    let mut returns = 0u64;
+    for slot in first..=current {
+        if let Some(value) = accumulator(&slot_key(window, slot, token_id, "squared_returns")) {
+            squared_returns += value;
+        }
+        returns += accumulator(&slot_key(window, slot, token_id, "returns")).map(|r| r.to_u64()).unwrap_or(0);
+    }
+
+    if returns == 0 {
+        return None;
+    }
+    let variance = BigDecimal::from_str(&squared_returns.to_string()).unwrap_or_default()
+        / BigDecimal::from(10u64.pow(RETURN_DECIMALS));
+    Some((variance.sqrt().unwrap_or_default(), returns))
+}
@@ -0,0 +1,200 @@
+use std::str::FromStr;
+use substreams::scalar::BigInt;
+
+use crate::pb::polymarket::orderbook::v1::OrderFilledEvent;
+use crate::utils;
+
+/// Counterparty pairs are counted in windows of this length; a fill is compared against the
+/// current and previous window
+pub const PAIR_WINDOW_SECONDS: u64 = 3600;
+
+/// Wash-trading thresholds parsed from module params.
+///
+/// Params are whitespace-separated `key:value` terms, e.g. `min_round_trips:2`.
+#[derive(Debug, Clone)]
+pub struct WashParams {
+    /// Fills each way between the same two addresses in one token before the pair is circular
+    pub min_round_trips: u64,
+}
+
+impl Default for WashParams {
+    fn default() -> Self {
+        WashParams { min_round_trips: 2 }
+    }
+}
+
+impl WashParams {
+    pub fn parse(params: &str) -> Result<Self, substreams::errors::Error> {
+        let mut parsed = WashParams::default();
+
+        for term in params.split_whitespace() {
+            let (key, value) = term
+                .split_once(':')
+                .ok_or_else(|| anyhow::anyhow!("invalid wash trade term {:?}, expected key:value", term))?;
+
+            match key {
+                "min_round_trips" => {
+                    parsed.min_round_trips = value
+                        .parse::<u64>()
+                        .ok()
+                        .filter(|v| *v > 0)
+                        .ok_or_else(|| anyhow::anyhow!("invalid min_round_trips {:?}, expected a positive integer", value))?;
+                }
+                _ => return Err(anyhow::anyhow!("unknown wash trade key {:?}", key)),
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+/// Window a block timestamp falls in
+pub fn window_of(timestamp_seconds: u64) -> u64 {
+    timestamp_seconds / PAIR_WINDOW_SECONDS
+}
+
+/// Key prefix of every pair counter of `window`, for pruning windows no fill compares against
+pub fn window_prefix(window: u64) -> String {
+    format!("{}:", window)
+}
+
+/// Fills in which `buyer` received `token_id` from `seller`
+pub fn pair_key(window: u64, token_id: &str, buyer: &str, seller: &str) -> String {
+    format!("{}:{}:{}:{}", window, token_id, buyer, seller)
+}
+
+/// Address that received outcome tokens and address that gave them up. A maker paying
+/// collateral bought from the taker; otherwise the maker sold to the taker.
+pub fn buyer_and_seller(fill: &OrderFilledEvent) -> (&str, &str) {
+    if fill.maker_asset_id == "0" {
+        (&fill.maker, &fill.taker)
+    } else {
+        (&fill.taker, &fill.maker)
+    }
+}
+
+/// Whether a fill is between two traders. When orders are matched, the taker order is filled
+/// against the exchange itself, which is not a counterparty.
+pub fn has_counterparty(fill: &OrderFilledEvent) -> bool {
+    fill.taker != fill.call_address
+}
+
+/// Why a fill looks like a wash trade
+#[derive(Debug, Clone, PartialEq)]
+pub enum WashSignal {
+    /// Maker and taker are the same address
+    SelfTrade,
+    /// The two addresses traded the token both ways at least `min_round_trips` times each over the
+    /// current and previous window; `forward` and `reverse` count this fill's direction and the other
+    Circular { forward: u64, reverse: u64 },
+}
+
+impl WashSignal {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            WashSignal::SelfTrade => "self_trade",
+            WashSignal::Circular { .. } => "circular",
+        }
+    }
+}
+
+/// Wash signal of a fill at `now`, reading the pair counters of `store_counterparty_pairs` through
+/// `pair_count(pair_key(..))`
+pub fn wash_signal(
+    params: &WashParams,
+    fill: &OrderFilledEvent,
+    now: u64,
+    pair_count: impl Fn(&str) -> Option<BigInt>,
+) -> Option<WashSignal> {
+    if fill.maker == fill.taker {
+        return Some(WashSignal::SelfTrade);
+    }
+    if !has_counterparty(fill) {
+        return None;
+    }
+
+    let (buyer, seller) = buyer_and_seller(fill);
+    let window = window_of(now);
+    let count = |buyer: &str, seller: &str| -> u64 {
+        [window.saturating_sub(1), window]
+            .iter()
+            .filter_map(|w| pair_count(&pair_key(*w, &fill.token_id, buyer, seller)))
+            .map(|c| c.to_u64())
+            .sum()
+    };
+    let (forward, reverse) = (count(buyer, seller), count(seller, buyer));
+
+    (forward >= params.min_round_trips && reverse >= params.min_round_trips)
+        .then_some(WashSignal::Circular { forward, reverse })
+}
+
+/// A fill flagged as a wash trade, attributed to the pair that traded it
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlaggedFill<'a> {
+    pub fill: &'a OrderFilledEvent,
+    pub signal: WashSignal,
+    pub buyer: String,
+    pub seller: String,
+    /// Collateral of the fill that was washed, left out of wash-adjusted volume
+    pub collateral: BigInt,
+}
+
+/// Wash signals of a block's fills, in fill order.
+///
+/// When orders are matched, each maker order is filled against the taker, then the taker order is
+/// filled against the exchange for the same volume. That taker-order fill has no counterparty of
+/// its own, so it takes the signal and pair of the first flagged maker fill it matched, and the
+/// collateral of all of them.
+pub fn flag_fills<'a>(
+    params: &WashParams,
+    fills: &'a [OrderFilledEvent],
+    now: u64,
+    pair_count: impl Fn(&str) -> Option<BigInt>,
+) -> Vec<FlaggedFill<'a>> {
+    let mut flagged = vec![];
+    // Flagged maker fills not yet claimed by a taker-order fill, by transaction and taker
+    let mut pending: Vec<(&str, &str, FlaggedFill)> = vec![];
+
+    for fill in fills {
+        if let Some(signal) = wash_signal(params, fill, now, &pair_count) {
+            let (buyer, seller) = buyer_and_seller(fill);
+            let flag = FlaggedFill {
+                fill,
+                signal,
+                buyer: buyer.to_string(),
+                seller: seller.to_string(),
+                collateral: amount(utils::collateral_amount(fill)),
+            };
+            pending.push((&fill.transaction_hash, &fill.taker, flag.clone()));
+            flagged.push(flag);
+            continue;
+        }
+        if has_counterparty(fill) {
+            continue;
+        }
+
+        let (matched, rest): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|(trx, taker, _)| *trx == fill.transaction_hash && *taker == fill.maker);
+        pending = rest;
+        let Some((_, _, first)) = matched.first() else {
+            continue;
+        };
+
+        let washed = matched.iter().fold(BigInt::zero(), |total, (_, _, f)| total + f.collateral.clone());
+        let collateral = amount(utils::collateral_amount(fill));
+        flagged.push(FlaggedFill {
+            fill,
+            signal: first.signal.clone(),
+            buyer: first.buyer.clone(),
+            seller: first.seller.clone(),
+            collateral: if washed < collateral { washed } else { collateral },
+        });
+    }
+
+    flagged
+}
+
+fn amount(value: &str) -> BigInt {
+    BigInt::from_str(value).unwrap_or_else(|_| BigInt::zero())
+}
@@ -17,17 +17,54 @@ CREATE TABLE IF NOT EXISTS order_fills (
     side VARCHAR(10) NOT NULL,
     price NUMERIC(38, 18) NOT NULL,
     block_number BIGINT NOT NULL,
+    block_hash VARCHAR(66) NOT NULL,
+    block_timestamp TIMESTAMP NOT NULL,
+    log_index INTEGER NOT NULL DEFAULT 0,
+    ordinal BIGINT NOT NULL DEFAULT 0,
     -- V2 additions
     exchange_version VARCHAR(4) NOT NULL DEFAULT 'v1',
+    exchange VARCHAR(10) NOT NULL DEFAULT 'ctf',  -- 'ctf' | 'neg_risk'
     token_id VARCHAR,            -- conditional token ID (v1: derived; v2: emitted directly)
     side_raw SMALLINT NOT NULL DEFAULT 0,
     builder VARCHAR(66),         -- bytes32 builder code (v2 only, hex-encoded)
     metadata VARCHAR(66),        -- bytes32 metadata (v2 only, hex-encoded)
+    metadata_layout VARCHAR(16), -- 'empty' | 'utf8_tag' | 'app_id' | 'address' | 'unknown' (v2 only)
+    metadata_value VARCHAR(64),  -- decoded tag, app ID or address
+    -- Transaction context
+    tx_from VARCHAR(42),         -- transaction sender (operator relayer or direct caller)
+    tx_to VARCHAR(42),
+    tx_index INTEGER,
+    gas_used BIGINT,
+    effective_gas_price NUMERIC(78, 0),
+    call_address VARCHAR(42),    -- address of the call that emitted the log
     created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
 );
 
+-- Columns added since v0.3.1, for tables created by an earlier schema. Rows written before the
+-- upgrade keep these placeholder defaults until the range is resynced.
+ALTER TABLE order_fills
+    ADD COLUMN IF NOT EXISTS block_hash VARCHAR(66) NOT NULL DEFAULT '',
+    ADD COLUMN IF NOT EXISTS block_timestamp TIMESTAMP NOT NULL DEFAULT '1970-01-01 00:00:00',
+    ADD COLUMN IF NOT EXISTS log_index INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS ordinal BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS exchange_version VARCHAR(4) NOT NULL DEFAULT 'v1',
+    ADD COLUMN IF NOT EXISTS exchange VARCHAR(10) NOT NULL DEFAULT 'ctf',
+    ADD COLUMN IF NOT EXISTS token_id VARCHAR,
+    ADD COLUMN IF NOT EXISTS side_raw SMALLINT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS builder VARCHAR(66),
+    ADD COLUMN IF NOT EXISTS metadata VARCHAR(66),
+    ADD COLUMN IF NOT EXISTS metadata_layout VARCHAR(16),
+    ADD COLUMN IF NOT EXISTS metadata_value VARCHAR(64),
+    ADD COLUMN IF NOT EXISTS tx_from VARCHAR(42),
+    ADD COLUMN IF NOT EXISTS tx_to VARCHAR(42),
+    ADD COLUMN IF NOT EXISTS tx_index INTEGER,
+    ADD COLUMN IF NOT EXISTS gas_used BIGINT,
+    ADD COLUMN IF NOT EXISTS effective_gas_price NUMERIC(78, 0),
+    ADD COLUMN IF NOT EXISTS call_address VARCHAR(42);
+
 -- Indexes for order_fills
 CREATE INDEX IF NOT EXISTS idx_order_fills_block ON order_fills(block_number);
+CREATE INDEX IF NOT EXISTS idx_order_fills_block_timestamp ON order_fills(block_timestamp);
 CREATE INDEX IF NOT EXISTS idx_order_fills_maker ON order_fills(maker);
 CREATE INDEX IF NOT EXISTS idx_order_fills_taker ON order_fills(taker);
 CREATE INDEX IF NOT EXISTS idx_order_fills_maker_asset ON order_fills(maker_asset_id);
@@ -35,6 +72,7 @@ CREATE INDEX IF NOT EXISTS idx_order_fills_tx_hash ON order_fills(transaction_ha
 CREATE INDEX IF NOT EXISTS idx_order_fills_token_id ON order_fills(token_id);
 CREATE INDEX IF NOT EXISTS idx_order_fills_version ON order_fills(exchange_version);
 CREATE INDEX IF NOT EXISTS idx_order_fills_builder ON order_fills(builder) WHERE builder IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_order_fills_tx_from ON order_fills(tx_from);
 
 -- Market Orderbooks Table
 -- Aggregated market-level statistics
@@ -45,13 +83,31 @@ CREATE TABLE IF NOT EXISTS market_orderbooks (
     buys_quantity BIGINT NOT NULL DEFAULT 0,
     sells_quantity BIGINT NOT NULL DEFAULT 0,
     collateral_volume NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    wash_adjusted_volume NUMERIC(78, 0) NOT NULL DEFAULT 0,
     average_trade_size NUMERIC(78, 18) NOT NULL DEFAULT 0,
     total_fees NUMERIC(78, 0) NOT NULL DEFAULT 0,
     mid_price NUMERIC(38, 18) NOT NULL DEFAULT 0,
+    best_bid NUMERIC(38, 18),
+    best_ask NUMERIC(38, 18),
+    spread NUMERIC(38, 18) NOT NULL DEFAULT 0,
+    market_depth NUMERIC(78, 18) NOT NULL DEFAULT 0,
+    volatility NUMERIC(38, 18) NOT NULL DEFAULT 0,
     last_updated_block BIGINT NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash VARCHAR(66) NOT NULL,
     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
 );
 
+ALTER TABLE market_orderbooks
+    ADD COLUMN IF NOT EXISTS wash_adjusted_volume NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS best_bid NUMERIC(38, 18),
+    ADD COLUMN IF NOT EXISTS best_ask NUMERIC(38, 18),
+    ADD COLUMN IF NOT EXISTS spread NUMERIC(38, 18) NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS market_depth NUMERIC(78, 18) NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS volatility NUMERIC(38, 18) NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS block_number BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS block_hash VARCHAR(66) NOT NULL DEFAULT '';
+
 -- Indexes for market_orderbooks
 CREATE INDEX IF NOT EXISTS idx_market_orderbooks_condition ON market_orderbooks(condition_id);
 CREATE INDEX IF NOT EXISTS idx_market_orderbooks_volume ON market_orderbooks(collateral_volume DESC);
@@ -66,9 +122,15 @@ CREATE TABLE IF NOT EXISTS trader_accounts (
     total_fees NUMERIC(78, 0) NOT NULL DEFAULT 0,
     is_active BOOLEAN NOT NULL DEFAULT true,
     trader_type VARCHAR(20) NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash VARCHAR(66) NOT NULL,
     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
 );
 
+ALTER TABLE trader_accounts
+    ADD COLUMN IF NOT EXISTS block_number BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS block_hash VARCHAR(66) NOT NULL DEFAULT '';
+
 -- Indexes for trader_accounts
 CREATE INDEX IF NOT EXISTS idx_trader_accounts_volume ON trader_accounts(total_volume DESC);
 CREATE INDEX IF NOT EXISTS idx_trader_accounts_trades ON trader_accounts(trades_quantity DESC);
@@ -85,9 +147,108 @@ CREATE TABLE IF NOT EXISTS global_stats (
     total_fees NUMERIC(78, 0) NOT NULL DEFAULT 0,
     average_trade_size NUMERIC(78, 18) NOT NULL DEFAULT 0,
     platform_fee_revenue NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    block_number BIGINT NOT NULL,
+    block_hash VARCHAR(66) NOT NULL,
+    updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE global_stats
+    ADD COLUMN IF NOT EXISTS block_number BIGINT NOT NULL DEFAULT 0,
+    ADD COLUMN IF NOT EXISTS block_hash VARCHAR(66) NOT NULL DEFAULT '';
+
+-- Aggregate Snapshots
+-- One row per (id, block_number) for every block an aggregate changed in.
+-- Rows above a reorged block can be deleted and the latest tables rebuilt from the
+-- highest remaining snapshot; they also double as a per-block history.
+CREATE TABLE IF NOT EXISTS market_orderbook_snapshots (
+    id VARCHAR NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash VARCHAR(66) NOT NULL,
+    condition_id VARCHAR NOT NULL,
+    trades_quantity BIGINT NOT NULL DEFAULT 0,
+    buys_quantity BIGINT NOT NULL DEFAULT 0,
+    sells_quantity BIGINT NOT NULL DEFAULT 0,
+    collateral_volume NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    wash_adjusted_volume NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    average_trade_size NUMERIC(78, 18) NOT NULL DEFAULT 0,
+    total_fees NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    mid_price NUMERIC(38, 18) NOT NULL DEFAULT 0,
+    best_bid NUMERIC(38, 18),
+    best_ask NUMERIC(38, 18),
+    spread NUMERIC(38, 18) NOT NULL DEFAULT 0,
+    market_depth NUMERIC(78, 18) NOT NULL DEFAULT 0,
+    volatility NUMERIC(38, 18) NOT NULL DEFAULT 0,
+    last_updated_block BIGINT NOT NULL,
+    PRIMARY KEY (id, block_number)
+);
+
+CREATE TABLE IF NOT EXISTS trader_account_snapshots (
+    id VARCHAR NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash VARCHAR(66) NOT NULL,
+    trades_quantity BIGINT NOT NULL DEFAULT 0,
+    total_volume NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    total_fees NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    is_active BOOLEAN NOT NULL DEFAULT true,
+    trader_type VARCHAR(20) NOT NULL,
+    PRIMARY KEY (id, block_number)
+);
+
+CREATE TABLE IF NOT EXISTS global_stats_snapshots (
+    id VARCHAR NOT NULL DEFAULT 'global',
+    block_number BIGINT NOT NULL,
+    block_hash VARCHAR(66) NOT NULL,
+    trades_quantity BIGINT NOT NULL DEFAULT 0,
+    buys_quantity BIGINT NOT NULL DEFAULT 0,
+    sells_quantity BIGINT NOT NULL DEFAULT 0,
+    collateral_volume NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    total_fees NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    average_trade_size NUMERIC(78, 18) NOT NULL DEFAULT 0,
+    platform_fee_revenue NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    PRIMARY KEY (id, block_number)
+);
+
+CREATE INDEX IF NOT EXISTS idx_market_orderbook_snapshots_block ON market_orderbook_snapshots(block_number);
+CREATE INDEX IF NOT EXISTS idx_trader_account_snapshots_block ON trader_account_snapshots(block_number);
+CREATE INDEX IF NOT EXISTS idx_global_stats_snapshots_block ON global_stats_snapshots(block_number);
+
+-- Consistency Violations Table
+-- Fill/match invariants that failed, from map_consistency_checks
+CREATE TABLE IF NOT EXISTS consistency_violations (
+    id VARCHAR PRIMARY KEY,
+    transaction_hash VARCHAR(66) NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash VARCHAR(66) NOT NULL,
+    block_timestamp TIMESTAMP NOT NULL,
+    ordinal BIGINT NOT NULL,
+    exchange VARCHAR(10) NOT NULL,
+    exchange_version VARCHAR(4) NOT NULL,
+    check_name VARCHAR(32) NOT NULL,  -- 'taker_fill_vs_match' | 'maker_fill_sum' | 'missing_taker_fill' | 'price_out_of_range' | 'fee_above_max_rate'
+    order_hash VARCHAR(66),
+    expected VARCHAR NOT NULL,
+    actual VARCHAR NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_consistency_violations_block ON consistency_violations(block_number);
+CREATE INDEX IF NOT EXISTS idx_consistency_violations_check ON consistency_violations(check_name);
+
+-- Builder Stats Table
+-- Flow routed through each v2 builder code, from map_builder_stats
+CREATE TABLE IF NOT EXISTS builder_stats (
+    id VARCHAR(64) PRIMARY KEY,
+    trades_quantity BIGINT NOT NULL DEFAULT 0,
+    collateral_volume NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    total_fees NUMERIC(78, 0) NOT NULL DEFAULT 0,
+    unique_traders BIGINT NOT NULL DEFAULT 0,
+    unique_markets BIGINT NOT NULL DEFAULT 0,
+    last_active_block BIGINT NOT NULL,
+    block_number BIGINT NOT NULL,
+    block_hash VARCHAR(66) NOT NULL,
     updated_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
 );
 
+CREATE INDEX IF NOT EXISTS idx_builder_stats_volume ON builder_stats(collateral_volume DESC);
+
 -- Cursors table for substreams-sink-sql
 CREATE TABLE IF NOT EXISTS cursors (
     id VARCHAR PRIMARY KEY,
@@ -134,8 +295,9 @@ SELECT
     taker_amount_filled,
     price,
     side,
-    block_number
+    block_number,
+    block_timestamp
 FROM order_fills
 WHERE taker_amount_filled > 1000000000000000000000  -- > 1000 USDC
-ORDER BY block_number DESC
+ORDER BY block_number DESC, log_index DESC
 LIMIT 100;
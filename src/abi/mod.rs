@@ -1,4 +0,0 @@
-pub mod ctf_exchange;
-pub mod neg_risk_exchange;
-pub mod ctf_exchange_v2;
-pub mod neg_risk_exchange_v2;
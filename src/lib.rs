@@ -1,468 +1,160 @@
-use substreams::Hex;
-use substreams::store::{StoreNew, StoreSet, StoreSetProto, StoreGet, StoreGetProto, Deltas, DeltaProto};
+// The handler macros turn `params: String` into a raw pointer argument that the generated code dereferences
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
+use substreams::pb::sf::substreams::index::v1::Keys;
+use substreams::pb::substreams::Clock;
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::scalar::BigInt;
+use substreams::store::{StoreNew, StoreSet, StoreSetProto, StoreGet, StoreGetProto, StoreSetBigInt, StoreGetBigInt, StoreAdd, StoreAddBigInt, StoreSetString, Delta, Deltas, DeltaProto, DeltaString};
 use substreams_ethereum::pb::eth::v2 as eth;
-use substreams_ethereum::Event;
 use substreams_database_change::pb::sf::substreams::sink::database::v1::DatabaseChanges;
-use substreams_database_change::tables::Tables;
-use prost_types::Timestamp;
+use substreams_database_change::tables::{Row, Tables};
+use prost::Message;
+use std::collections::BTreeMap;
 use std::str::FromStr;
 use bigdecimal::BigDecimal;
 
-mod abi;
-mod utils;
+use polymarket_orderbook_core::{conditions, consistency, filters, utils};
 
-#[path = "pb/mod.rs"]
-#[allow(dead_code)]
-pub mod pb;
+pub use polymarket_orderbook_core::{alerts, builders, exchange_index_keys, extract_exchange_events, ladder, metadata, neg_risk, pb, registry, twap, volatility, wash};
 
+use pb::sf::substreams::sink::entity::v1::{entity_change, value, EntityChange, EntityChanges, Field, Value};
+use pb::sf::substreams::sink::kv::v1::{kv_operation, KvOperation, KvOperations};
 use pb::polymarket::orderbook::v1::{
-    OrderFilledEvent, OrderFilledEvents, OrdersMatchedEvent, OrdersMatchedEvents,
+    ExchangeEvents, OrderFilledEvents, OrdersMatchedEvents, ConsistencyViolations, UndecodedLogs, MarketAlerts,
+    NegRiskQuestion, NegRiskQuestions, RegisteredToken, OutcomePrice, TradingOpportunities, TokenPrice, ConditionPrices,
+    TwapPrice, TwapPrices, TokenVolatility, TokenVolatilities, Builder, Builders,
+    MetadataLayoutCount, MetadataLayouts, UnknownMetadata, WashTradeSignal, WashTradeSignals,
     MarketOrderbook, MarketOrderbooks, Account, Accounts, GlobalOrderbookStats,
 };
 
 substreams_ethereum::init!();
 
-/// Extract OrderFilled events from CTF Exchange (v1)
-#[substreams::handlers::map]
-pub fn map_ctf_exchange_order_filled(blk: eth::Block) -> Result<OrderFilledEvents, substreams::errors::Error> {
-    let mut events = vec![];
-
-    for trx in &blk.transaction_traces {
-        for call in &trx.calls {
-            for log in &call.logs {
-                if log.address != Hex::decode("4bfb41d5b3570defd03c39a9a4d8de6bd8b8982e").unwrap() {
-                    continue;
-                }
-
-                if let Some(event) = abi::ctf_exchange::events::OrderFilled::match_and_decode(&log) {
-                    let order_id = utils::generate_order_id(&Hex::encode(&trx.hash), &Hex::encode(&event.order_hash));
-
-                    events.push(OrderFilledEvent {
-                        id: order_id,
-                        transaction_hash: Hex::encode(&trx.hash),
-                        timestamp: Some(Timestamp {
-                            seconds: blk.timestamp_seconds() as i64,
-                            nanos: 0,
-                        }),
-                        order_hash: Hex::encode(&event.order_hash),
-                        maker: Hex::encode(&event.maker),
-                        taker: Hex::encode(&event.taker),
-                        maker_asset_id: event.maker_asset_id.to_string(),
-                        taker_asset_id: event.taker_asset_id.to_string(),
-                        maker_amount_filled: event.maker_amount_filled.to_string(),
-                        taker_amount_filled: event.taker_amount_filled.to_string(),
-                        fee: event.fee.to_string(),
-                        block_number: blk.number,
-                        side: utils::determine_trade_side(
-                            &event.maker_asset_id,
-                            &event.taker_asset_id,
-                            &event.maker_amount_filled,
-                            &event.taker_amount_filled,
-                        ),
-                        price: utils::calculate_price(&event.maker_amount_filled, &event.taker_amount_filled).to_string(),
-                        ordinal: log.ordinal,
-                        exchange_version: "v1".to_string(),
-                        token_id: utils::v1_token_id(&event.maker_asset_id, &event.taker_asset_id),
-                        side_raw: 0,
-                        builder: String::new(),
-                        metadata: String::new(),
-                    });
-                }
-            }
-        }
-    }
+// ============================================
+// Block Index (Layer 0)
+// ============================================
 
-    Ok(OrderFilledEvents {
-        events,
-        block_number: blk.number,
-        block_hash: Hex::encode(&blk.hash),
-        timestamp: Some(Timestamp {
-            seconds: blk.timestamp_seconds() as i64,
-            nanos: 0,
-        }),
-    })
+/// Emits keys for every log emitted by an exchange in the contract registry (see `registry`),
+/// so the extraction maps can skip unrelated blocks via `blockFilter`:
+/// `evt_addr:0x{address}`, `evt_sig:0x{topic0}`, `exchange:{kind}_{version}` and `exchange:any`.
+/// Params must match those given to `map_exchange_events`.
+#[substreams::handlers::map]
+pub fn index_polymarket_events(params: String, blk: eth::Block) -> Result<Keys, substreams::errors::Error> {
+    let registry = registry::ContractRegistry::from_params(&params)?;
+    Ok(exchange_index_keys(&registry, &blk))
 }
 
-/// Extract OrderFilled events from Neg Risk Exchange (v1)
-#[substreams::handlers::map]
-pub fn map_neg_risk_exchange_order_filled(blk: eth::Block) -> Result<OrderFilledEvents, substreams::errors::Error> {
-    let mut events = vec![];
-
-    for trx in &blk.transaction_traces {
-        for call in &trx.calls {
-            for log in &call.logs {
-                if log.address != Hex::decode("C5d563A36AE78145C45a50134d48A1215220f80a").unwrap() {
-                    continue;
-                }
-
-                if let Some(event) = abi::neg_risk_exchange::events::OrderFilled::match_and_decode(&log) {
-                    let order_id = utils::generate_order_id(&Hex::encode(&trx.hash), &Hex::encode(&event.order_hash));
-
-                    events.push(OrderFilledEvent {
-                        id: order_id,
-                        transaction_hash: Hex::encode(&trx.hash),
-                        timestamp: Some(Timestamp {
-                            seconds: blk.timestamp_seconds() as i64,
-                            nanos: 0,
-                        }),
-                        order_hash: Hex::encode(&event.order_hash),
-                        maker: Hex::encode(&event.maker),
-                        taker: Hex::encode(&event.taker),
-                        maker_asset_id: event.maker_asset_id.to_string(),
-                        taker_asset_id: event.taker_asset_id.to_string(),
-                        maker_amount_filled: event.maker_amount_filled.to_string(),
-                        taker_amount_filled: event.taker_amount_filled.to_string(),
-                        fee: event.fee.to_string(),
-                        block_number: blk.number,
-                        side: utils::determine_trade_side(
-                            &event.maker_asset_id,
-                            &event.taker_asset_id,
-                            &event.maker_amount_filled,
-                            &event.taker_amount_filled,
-                        ),
-                        price: utils::calculate_price(&event.maker_amount_filled, &event.taker_amount_filled).to_string(),
-                        ordinal: log.ordinal,
-                        exchange_version: "v1".to_string(),
-                        token_id: utils::v1_token_id(&event.maker_asset_id, &event.taker_asset_id),
-                        side_raw: 0,
-                        builder: String::new(),
-                        metadata: String::new(),
-                    });
-                }
-            }
-        }
-    }
+// ============================================
+// Event Extraction (Layer 1)
+// ============================================
 
-    Ok(OrderFilledEvents {
-        events,
-        block_number: blk.number,
-        block_hash: Hex::encode(&blk.hash),
-        timestamp: Some(Timestamp {
-            seconds: blk.timestamp_seconds() as i64,
-            nanos: 0,
-        }),
-    })
+/// Scans every log of the block once. Logs from an exchange in the contract registry are
+/// dispatched by address to the v1 or v2 decoders and emitted as fills, matches or admin events.
+/// Params override the registry (see `registry::ContractRegistry`); empty params index Polygon mainnet.
+#[substreams::handlers::map]
+pub fn map_exchange_events(params: String, blk: eth::Block) -> Result<ExchangeEvents, substreams::errors::Error> {
+    let registry = registry::ContractRegistry::from_params(&params)?;
+    Ok(extract_exchange_events(&registry, &blk))
 }
 
-/// Extract OrderFilled events from CTF Exchange V2 (deployed block 84902353)
+/// QuestionPrepared events from the NegRiskAdapter, each with the CTF condition it prepared.
+/// Params list adapter addresses (see `neg_risk::adapters_from_params`); empty params use Polygon mainnet.
 #[substreams::handlers::map]
-pub fn map_ctf_exchange_v2_order_filled(blk: eth::Block) -> Result<OrderFilledEvents, substreams::errors::Error> {
-    extract_v2_order_filled(
-        &blk,
-        &Hex::decode("E111180000d2663C0091e4f400237545B87B996B").unwrap(),
-        |log| abi::ctf_exchange_v2::events::OrderFilled::match_and_decode(log),
-    )
+pub fn map_neg_risk_questions(params: String, blk: eth::Block) -> Result<NegRiskQuestions, substreams::errors::Error> {
+    let adapters = neg_risk::adapters_from_params(&params)?;
+    Ok(neg_risk::extract_neg_risk_questions(&adapters, &blk))
 }
 
-/// Extract OrderFilled events from Neg Risk CTF Exchange V2
-#[substreams::handlers::map]
-pub fn map_neg_risk_exchange_v2_order_filled(blk: eth::Block) -> Result<OrderFilledEvents, substreams::errors::Error> {
-    extract_v2_order_filled(
-        &blk,
-        &Hex::decode("e2222d279d744050d28e00520010520000310F59").unwrap(),
-        |log| abi::neg_risk_exchange_v2::events::OrderFilled::match_and_decode(log),
-    )
-}
-
-/// Generic v2 OrderFilled extractor (CTF and Neg Risk share an identical event shape)
-fn extract_v2_order_filled<F, E>(
-    blk: &eth::Block,
-    contract_address: &[u8],
-    decode: F,
-) -> Result<OrderFilledEvents, substreams::errors::Error>
-where
-    F: Fn(&eth::Log) -> Option<E>,
-    E: V2OrderFilled,
-{
-    let mut events = vec![];
-
-    for trx in &blk.transaction_traces {
-        for call in &trx.calls {
-            for log in &call.logs {
-                if log.address != contract_address {
-                    continue;
-                }
-
-                if let Some(event) = decode(log) {
-                    let order_hash = event.order_hash();
-                    let order_id = utils::generate_order_id(&Hex::encode(&trx.hash), &Hex::encode(order_hash));
-                    let token_id = event.token_id().to_string();
-                    let side_raw = event.side();
-                    let (maker_asset_id, taker_asset_id) = utils::v2_assets_from_side(side_raw, &token_id);
-                    let side_str = match side_raw {
-                        0 => "buy",
-                        1 => "sell",
-                        _ => "unknown",
-                    }
-                    .to_string();
-
-                    events.push(OrderFilledEvent {
-                        id: order_id,
-                        transaction_hash: Hex::encode(&trx.hash),
-                        timestamp: Some(Timestamp {
-                            seconds: blk.timestamp_seconds() as i64,
-                            nanos: 0,
-                        }),
-                        order_hash: Hex::encode(order_hash),
-                        maker: Hex::encode(event.maker()),
-                        taker: Hex::encode(event.taker()),
-                        maker_asset_id,
-                        taker_asset_id,
-                        maker_amount_filled: event.maker_amount_filled().to_string(),
-                        taker_amount_filled: event.taker_amount_filled().to_string(),
-                        fee: event.fee().to_string(),
-                        block_number: blk.number,
-                        side: side_str,
-                        price: utils::calculate_price(event.maker_amount_filled(), event.taker_amount_filled()).to_string(),
-                        ordinal: log.ordinal,
-                        exchange_version: "v2".to_string(),
-                        token_id,
-                        side_raw: side_raw as u32,
-                        builder: Hex::encode(event.builder()),
-                        metadata: Hex::encode(event.metadata()),
-                    });
-                }
-            }
-        }
-    }
+// ============================================
+// Per-Exchange Views (Layer 1, backwards compatible)
+// ============================================
 
-    Ok(OrderFilledEvents {
-        events,
-        block_number: blk.number,
-        block_hash: Hex::encode(&blk.hash),
-        timestamp: Some(Timestamp {
-            seconds: blk.timestamp_seconds() as i64,
-            nanos: 0,
-        }),
-    })
+/// OrderFilled events from CTF Exchange (v1)
+#[substreams::handlers::map]
+pub fn map_ctf_exchange_order_filled(events: ExchangeEvents) -> Result<OrderFilledEvents, substreams::errors::Error> {
+    Ok(order_fills_view(events, "ctf", "v1"))
 }
 
-/// Trait that abstracts over the two structurally-identical v2 OrderFilled types
-/// (Abigen generates a distinct struct per ABI even when fields match).
-trait V2OrderFilled {
-    fn order_hash(&self) -> &[u8];
-    fn maker(&self) -> &[u8];
-    fn taker(&self) -> &[u8];
-    fn side(&self) -> u8;
-    fn token_id(&self) -> &substreams::scalar::BigInt;
-    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt;
-    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt;
-    fn fee(&self) -> &substreams::scalar::BigInt;
-    fn builder(&self) -> &[u8];
-    fn metadata(&self) -> &[u8];
-}
-
-impl V2OrderFilled for abi::ctf_exchange_v2::events::OrderFilled {
-    fn order_hash(&self) -> &[u8] { &self.order_hash }
-    fn maker(&self) -> &[u8] { &self.maker }
-    fn taker(&self) -> &[u8] { &self.taker }
-    fn side(&self) -> u8 { self.side.to_u64() as u8 }
-    fn token_id(&self) -> &substreams::scalar::BigInt { &self.token_id }
-    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.maker_amount_filled }
-    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.taker_amount_filled }
-    fn fee(&self) -> &substreams::scalar::BigInt { &self.fee }
-    fn builder(&self) -> &[u8] { &self.builder }
-    fn metadata(&self) -> &[u8] { &self.metadata }
-}
-
-impl V2OrderFilled for abi::neg_risk_exchange_v2::events::OrderFilled {
-    fn order_hash(&self) -> &[u8] { &self.order_hash }
-    fn maker(&self) -> &[u8] { &self.maker }
-    fn taker(&self) -> &[u8] { &self.taker }
-    fn side(&self) -> u8 { self.side.to_u64() as u8 }
-    fn token_id(&self) -> &substreams::scalar::BigInt { &self.token_id }
-    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.maker_amount_filled }
-    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.taker_amount_filled }
-    fn fee(&self) -> &substreams::scalar::BigInt { &self.fee }
-    fn builder(&self) -> &[u8] { &self.builder }
-    fn metadata(&self) -> &[u8] { &self.metadata }
-}
-
-/// Extract OrdersMatched events from CTF Exchange (v1)
+/// OrderFilled events from Neg Risk Exchange (v1)
 #[substreams::handlers::map]
-pub fn map_ctf_exchange_orders_matched(blk: eth::Block) -> Result<OrdersMatchedEvents, substreams::errors::Error> {
-    let mut events = vec![];
-
-    for trx in &blk.transaction_traces {
-        for call in &trx.calls {
-            for log in &call.logs {
-                if log.address != Hex::decode("4bfb41d5b3570defd03c39a9a4d8de6bd8b8982e").unwrap() {
-                    continue;
-                }
-
-                if let Some(event) = abi::ctf_exchange::events::OrdersMatched::match_and_decode(&log) {
-                    events.push(make_v1_orders_matched(&trx.hash, log.ordinal, blk.number, blk.timestamp_seconds(),
-                        event.maker_asset_id.to_string(),
-                        event.taker_asset_id.to_string(),
-                        event.maker_amount_filled.to_string(),
-                        event.taker_amount_filled.to_string()));
-                }
-            }
-        }
-    }
-
-    Ok(OrdersMatchedEvents {
-        events,
-        block_number: blk.number,
-        block_hash: Hex::encode(&blk.hash),
-        timestamp: Some(Timestamp { seconds: blk.timestamp_seconds() as i64, nanos: 0 }),
-    })
+pub fn map_neg_risk_exchange_order_filled(events: ExchangeEvents) -> Result<OrderFilledEvents, substreams::errors::Error> {
+    Ok(order_fills_view(events, "neg_risk", "v1"))
 }
 
-/// Extract OrdersMatched events from Neg Risk Exchange (v1)
+/// OrderFilled events from CTF Exchange V2 (deployed block 84902353)
 #[substreams::handlers::map]
-pub fn map_neg_risk_exchange_orders_matched(blk: eth::Block) -> Result<OrdersMatchedEvents, substreams::errors::Error> {
-    let mut events = vec![];
-
-    for trx in &blk.transaction_traces {
-        for call in &trx.calls {
-            for log in &call.logs {
-                if log.address != Hex::decode("C5d563A36AE78145C45a50134d48A1215220f80a").unwrap() {
-                    continue;
-                }
-
-                if let Some(event) = abi::neg_risk_exchange::events::OrdersMatched::match_and_decode(&log) {
-                    events.push(make_v1_orders_matched(&trx.hash, log.ordinal, blk.number, blk.timestamp_seconds(),
-                        event.maker_asset_id.to_string(),
-                        event.taker_asset_id.to_string(),
-                        event.maker_amount_filled.to_string(),
-                        event.taker_amount_filled.to_string()));
-                }
-            }
-        }
-    }
+pub fn map_ctf_exchange_v2_order_filled(events: ExchangeEvents) -> Result<OrderFilledEvents, substreams::errors::Error> {
+    Ok(order_fills_view(events, "ctf", "v2"))
+}
 
-    Ok(OrdersMatchedEvents {
-        events,
-        block_number: blk.number,
-        block_hash: Hex::encode(&blk.hash),
-        timestamp: Some(Timestamp { seconds: blk.timestamp_seconds() as i64, nanos: 0 }),
-    })
+/// OrderFilled events from Neg Risk CTF Exchange V2
+#[substreams::handlers::map]
+pub fn map_neg_risk_exchange_v2_order_filled(events: ExchangeEvents) -> Result<OrderFilledEvents, substreams::errors::Error> {
+    Ok(order_fills_view(events, "neg_risk", "v2"))
 }
 
-fn make_v1_orders_matched(
-    tx_hash: &[u8],
-    ordinal: u64,
-    block_number: u64,
-    block_ts: u64,
-    maker_asset_id: String,
-    taker_asset_id: String,
-    maker_amount_filled: String,
-    taker_amount_filled: String,
-) -> OrdersMatchedEvent {
-    OrdersMatchedEvent {
-        id: format!("{}-{}", Hex::encode(tx_hash), ordinal),
-        timestamp: Some(Timestamp { seconds: block_ts as i64, nanos: 0 }),
-        maker_asset_id,
-        taker_asset_id,
-        maker_amount_filled,
-        taker_amount_filled,
-        block_number,
-        ordinal,
-        exchange_version: "v1".to_string(),
-        taker_order_hash: String::new(),
-        taker_order_maker: String::new(),
-        token_id: String::new(),
-        side_raw: 0,
-    }
+/// OrdersMatched events from CTF Exchange (v1)
+#[substreams::handlers::map]
+pub fn map_ctf_exchange_orders_matched(events: ExchangeEvents) -> Result<OrdersMatchedEvents, substreams::errors::Error> {
+    Ok(orders_matched_view(events, "ctf", "v1"))
 }
 
-/// Extract OrdersMatched events from CTF Exchange V2
+/// OrdersMatched events from Neg Risk Exchange (v1)
 #[substreams::handlers::map]
-pub fn map_ctf_exchange_v2_orders_matched(blk: eth::Block) -> Result<OrdersMatchedEvents, substreams::errors::Error> {
-    extract_v2_orders_matched(
-        &blk,
-        &Hex::decode("E111180000d2663C0091e4f400237545B87B996B").unwrap(),
-        |log| abi::ctf_exchange_v2::events::OrdersMatched::match_and_decode(log),
-    )
+pub fn map_neg_risk_exchange_orders_matched(events: ExchangeEvents) -> Result<OrdersMatchedEvents, substreams::errors::Error> {
+    Ok(orders_matched_view(events, "neg_risk", "v1"))
 }
 
-/// Extract OrdersMatched events from Neg Risk CTF Exchange V2
+/// OrdersMatched events from CTF Exchange V2
 #[substreams::handlers::map]
-pub fn map_neg_risk_exchange_v2_orders_matched(blk: eth::Block) -> Result<OrdersMatchedEvents, substreams::errors::Error> {
-    extract_v2_orders_matched(
-        &blk,
-        &Hex::decode("e2222d279d744050d28e00520010520000310F59").unwrap(),
-        |log| abi::neg_risk_exchange_v2::events::OrdersMatched::match_and_decode(log),
-    )
-}
-
-fn extract_v2_orders_matched<F, E>(
-    blk: &eth::Block,
-    contract_address: &[u8],
-    decode: F,
-) -> Result<OrdersMatchedEvents, substreams::errors::Error>
-where
-    F: Fn(&eth::Log) -> Option<E>,
-    E: V2OrdersMatched,
-{
-    let mut events = vec![];
-
-    for trx in &blk.transaction_traces {
-        for call in &trx.calls {
-            for log in &call.logs {
-                if log.address != contract_address {
-                    continue;
-                }
-
-                if let Some(event) = decode(log) {
-                    let token_id = event.token_id().to_string();
-                    let side_raw = event.side();
-                    let (maker_asset_id, taker_asset_id) = utils::v2_assets_from_side(side_raw, &token_id);
-
-                    events.push(OrdersMatchedEvent {
-                        id: format!("{}-{}", Hex::encode(&trx.hash), log.ordinal),
-                        timestamp: Some(Timestamp { seconds: blk.timestamp_seconds() as i64, nanos: 0 }),
-                        maker_asset_id,
-                        taker_asset_id,
-                        maker_amount_filled: event.maker_amount_filled().to_string(),
-                        taker_amount_filled: event.taker_amount_filled().to_string(),
-                        block_number: blk.number,
-                        ordinal: log.ordinal,
-                        exchange_version: "v2".to_string(),
-                        taker_order_hash: Hex::encode(event.taker_order_hash()),
-                        taker_order_maker: Hex::encode(event.taker_order_maker()),
-                        token_id,
-                        side_raw: side_raw as u32,
-                    });
-                }
-            }
-        }
-    }
+pub fn map_ctf_exchange_v2_orders_matched(events: ExchangeEvents) -> Result<OrdersMatchedEvents, substreams::errors::Error> {
+    Ok(orders_matched_view(events, "ctf", "v2"))
+}
 
-    Ok(OrdersMatchedEvents {
-        events,
-        block_number: blk.number,
-        block_hash: Hex::encode(&blk.hash),
-        timestamp: Some(Timestamp { seconds: blk.timestamp_seconds() as i64, nanos: 0 }),
-    })
+/// OrdersMatched events from Neg Risk CTF Exchange V2
+#[substreams::handlers::map]
+pub fn map_neg_risk_exchange_v2_orders_matched(events: ExchangeEvents) -> Result<OrdersMatchedEvents, substreams::errors::Error> {
+    Ok(orders_matched_view(events, "neg_risk", "v2"))
 }
 
-trait V2OrdersMatched {
-    fn taker_order_hash(&self) -> &[u8];
-    fn taker_order_maker(&self) -> &[u8];
-    fn side(&self) -> u8;
-    fn token_id(&self) -> &substreams::scalar::BigInt;
-    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt;
-    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt;
+/// Fills of one exchange deployment; the body of the `map_*_order_filled` views
+pub fn order_fills_view(events: ExchangeEvents, exchange: &str, version: &str) -> OrderFilledEvents {
+    OrderFilledEvents {
+        events: events
+            .order_fills
+            .into_iter()
+            .filter(|e| e.exchange == exchange && e.exchange_version == version)
+            .collect(),
+        block_number: events.block_number,
+        block_hash: events.block_hash,
+        timestamp: events.timestamp,
+    }
 }
 
-impl V2OrdersMatched for abi::ctf_exchange_v2::events::OrdersMatched {
-    fn taker_order_hash(&self) -> &[u8] { &self.taker_order_hash }
-    fn taker_order_maker(&self) -> &[u8] { &self.taker_order_maker }
-    fn side(&self) -> u8 { self.side.to_u64() as u8 }
-    fn token_id(&self) -> &substreams::scalar::BigInt { &self.token_id }
-    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.maker_amount_filled }
-    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.taker_amount_filled }
+/// Matches of one exchange deployment; the body of the `map_*_orders_matched` views
+pub fn orders_matched_view(events: ExchangeEvents, exchange: &str, version: &str) -> OrdersMatchedEvents {
+    OrdersMatchedEvents {
+        events: events
+            .orders_matched
+            .into_iter()
+            .filter(|e| e.exchange == exchange && e.exchange_version == version)
+            .collect(),
+        block_number: events.block_number,
+        block_hash: events.block_hash,
+        timestamp: events.timestamp,
+    }
 }
 
-impl V2OrdersMatched for abi::neg_risk_exchange_v2::events::OrdersMatched {
-    fn taker_order_hash(&self) -> &[u8] { &self.taker_order_hash }
-    fn taker_order_maker(&self) -> &[u8] { &self.taker_order_maker }
-    fn side(&self) -> u8 { self.side.to_u64() as u8 }
-    fn token_id(&self) -> &substreams::scalar::BigInt { &self.token_id }
-    fn maker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.maker_amount_filled }
-    fn taker_amount_filled(&self) -> &substreams::scalar::BigInt { &self.taker_amount_filled }
+/// Logs from a registered exchange address that no decoder accepted: an unknown topic0 means the
+/// ABI gained an event, `decode_failed` means a known event changed layout. Empty on healthy blocks.
+#[substreams::handlers::map]
+pub fn map_undecoded_exchange_logs(events: ExchangeEvents) -> Result<UndecodedLogs, substreams::errors::Error> {
+    Ok(UndecodedLogs {
+        logs: events.undecoded_logs,
+        block_number: events.block_number,
+        block_hash: events.block_hash,
+        timestamp: events.timestamp,
+    })
 }
 
 // ============================================
@@ -472,23 +164,39 @@ impl V2OrdersMatched for abi::neg_risk_exchange_v2::events::OrdersMatched {
 /// Combines order fills from v1 + v2 CTF and Neg Risk exchanges into a single stream.
 #[substreams::handlers::map]
 pub fn map_all_order_fills(
-    ctf_fills: OrderFilledEvents,
-    neg_risk_fills: OrderFilledEvents,
-    ctf_v2_fills: OrderFilledEvents,
-    neg_risk_v2_fills: OrderFilledEvents,
+    clock: Clock,
+    events: ExchangeEvents,
 ) -> Result<OrderFilledEvents, substreams::errors::Error> {
-    let mut all_events = ctf_fills.events;
-    all_events.extend(neg_risk_fills.events);
-    all_events.extend(ctf_v2_fills.events);
-    all_events.extend(neg_risk_v2_fills.events);
+    Ok(combine_order_fills(clock, events))
+}
 
-    all_events.sort_by_key(|e| e.ordinal);
+/// The body of `map_all_order_fills`
+pub fn combine_order_fills(clock: Clock, events: ExchangeEvents) -> OrderFilledEvents {
+    // map_exchange_events is skipped by its blockFilter on blocks without exchange logs and then
+    // arrives empty, so block metadata comes from the clock
+    OrderFilledEvents {
+        events: events.order_fills,
+        block_number: clock.number,
+        block_hash: clock.id,
+        timestamp: clock.timestamp,
+    }
+}
+
+/// Narrow slice of `map_all_order_fills` selected by params, e.g.
+/// `token_id:1234 trader:0xabc... version:v2 exchange:neg_risk min_collateral:1000000`.
+/// See `filters::FillFilter` for the query syntax; empty params pass every fill through.
+#[substreams::handlers::map]
+pub fn map_filtered_order_fills(
+    params: String,
+    fills: OrderFilledEvents,
+) -> Result<OrderFilledEvents, substreams::errors::Error> {
+    let filter = filters::FillFilter::parse(&params)?;
 
     Ok(OrderFilledEvents {
-        events: all_events,
-        block_number: ctf_fills.block_number,
-        block_hash: ctf_fills.block_hash,
-        timestamp: ctf_fills.timestamp,
+        events: fills.events.into_iter().filter(|e| filter.matches(e)).collect(),
+        block_number: fills.block_number,
+        block_hash: fills.block_hash,
+        timestamp: fills.timestamp,
     })
 }
 
@@ -499,11 +207,17 @@ pub fn map_all_order_fills(
 /// Store market-level statistics indexed by asset ID
 #[substreams::handlers::store]
 pub fn store_markets(events: OrderFilledEvents, store: StoreSetProto<MarketOrderbook>) {
+    update_markets(events, &store);
+}
+
+/// The body of `store_markets`, generic over the store so it can run against an in-memory store
+pub fn update_markets<S: StoreSet<MarketOrderbook>>(events: OrderFilledEvents, store: &S) {
     for event in events.events {
-        let market_id = utils::extract_condition_id_from_str(&event.maker_asset_id);
+        // Key by the conditional token so BUY and SELL fills (collateral on opposite sides) share a market
+        let market_id = utils::extract_condition_id_from_str(&event.token_id);
 
         // Get existing or create new market orderbook
-        let mut orderbook = MarketOrderbook {
+        let orderbook = MarketOrderbook {
             id: market_id.clone(),
             condition_id: market_id.clone(),
             trades_quantity: 1,
@@ -526,6 +240,9 @@ pub fn store_markets(events: OrderFilledEvents, store: StoreSetProto<MarketOrder
             liquidity_score: "0".to_string(),
             market_depth: "0".to_string(),
             last_updated_block: events.block_number,
+            best_bid: String::new(),
+            best_ask: String::new(),
+            wash_adjusted_volume: String::new(),
         };
 
         store.set(event.ordinal, format!("market:{}", market_id), &orderbook);
@@ -535,6 +252,11 @@ pub fn store_markets(events: OrderFilledEvents, store: StoreSetProto<MarketOrder
 /// Store trader-level statistics indexed by address
 #[substreams::handlers::store]
 pub fn store_traders(events: OrderFilledEvents, store: StoreSetProto<Account>) {
+    update_traders(events, &store);
+}
+
+/// The body of `store_traders`
+pub fn update_traders<S: StoreSet<Account>>(events: OrderFilledEvents, store: &S) {
     for event in events.events {
         // Store for both maker and taker
         for (address, is_maker) in [(&event.maker, true), (&event.taker, false)] {
@@ -543,8 +265,8 @@ pub fn store_traders(events: OrderFilledEvents, store: StoreSetProto<Account>) {
                 trades_quantity: 1,
                 total_volume: event.taker_amount_filled.clone(),
                 total_fees: if is_maker { "0".to_string() } else { event.fee.clone() },
-                first_trade: event.timestamp.clone(),
-                last_trade: event.timestamp.clone(),
+                first_trade: event.timestamp,
+                last_trade: event.timestamp,
                 is_active: true,
                 trader_type: if is_maker { "maker".to_string() } else { "taker".to_string() },
                 volume_24h: "0".to_string(),
@@ -568,6 +290,11 @@ pub fn store_traders(events: OrderFilledEvents, store: StoreSetProto<Account>) {
 /// Store global platform statistics
 #[substreams::handlers::store]
 pub fn store_global_stats(events: OrderFilledEvents, store: StoreSetProto<GlobalOrderbookStats>) {
+    update_global_stats(events, &store);
+}
+
+/// The body of `store_global_stats`
+pub fn update_global_stats<S: StoreSet<GlobalOrderbookStats>>(events: OrderFilledEvents, store: &S) {
     if events.events.is_empty() {
         return;
     }
@@ -579,10 +306,10 @@ pub fn store_global_stats(events: OrderFilledEvents, store: StoreSetProto<Global
 
     for event in &events.events {
         if let Ok(vol) = BigDecimal::from_str(&event.taker_amount_filled) {
-            total_volume = total_volume + vol;
+            total_volume += vol;
         }
         if let Ok(fee) = BigDecimal::from_str(&event.fee) {
-            total_fees = total_fees + fee;
+            total_fees += fee;
         }
         if event.side == "buy" { buys += 1; } else { sells += 1; }
     }
@@ -605,7 +332,7 @@ pub fn store_global_stats(events: OrderFilledEvents, store: StoreSetProto<Global
         average_trade_size: avg_size,
         unique_traders: 0,
         active_markets: 0,
-        last_updated: events.timestamp.clone(),
+        last_updated: events.timestamp,
         total_liquidity: "0".to_string(),
         market_cap: "0".to_string(),
         volume_24h: "0".to_string(),
@@ -620,28 +347,481 @@ pub fn store_global_stats(events: OrderFilledEvents, store: StoreSetProto<Global
     store.set(0, "global", &stats);
 }
 
+/// Store the latest v2 `MaxFeeRateUpdated` value per exchange kind, keyed `max_fee_rate:{exchange}`
+#[substreams::handlers::store]
+pub fn store_max_fee_rates(events: ExchangeEvents, store: StoreSetBigInt) {
+    update_max_fee_rates(events, &store);
+}
+
+/// The body of `store_max_fee_rates`
+pub fn update_max_fee_rates<S: StoreSet<BigInt>>(events: ExchangeEvents, store: &S) {
+    for event in events.admin_events {
+        if event.event_type != "MaxFeeRateUpdated" {
+            continue;
+        }
+        if let Some(rate) = event.params.iter().find(|p| p.name == "max_fee_rate") {
+            let rate = BigInt::from_str(&rate.value).unwrap_or_else(|_| BigInt::zero());
+            store.set(event.ordinal, format!("max_fee_rate:{}", event.exchange), &rate);
+        }
+    }
+}
+
+/// Per-token activity counters bucketed into hourly windows, keyed `{window}:{metric}:{token_id}`
+/// (see `alerts::ACTIVITY_METRICS`); the rolling baselines of `map_market_alerts`.
+/// Only the current and previous windows are kept.
+#[substreams::handlers::store]
+pub fn store_market_activity(events: OrderFilledEvents, store: StoreAddBigInt) {
+    update_market_activity(events, &store);
+}
+
+/// The body of `store_market_activity`
+pub fn update_market_activity<S: StoreAdd<BigInt>>(events: OrderFilledEvents, store: &S) {
+    let seconds = events.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(0);
+    let window = alerts::window_of(seconds);
+    if window >= 2 {
+        store.delete_prefix(0, &alerts::window_prefix(window - 2));
+    }
+
+    for (token_id, activity) in alerts::block_activity(&events) {
+        let key = |metric: &str| alerts::activity_key(metric, &token_id, window);
+        store.add(0, key("volume"), activity.volume);
+        store.add(0, key("tokens"), activity.tokens);
+        store.add(0, key("fills"), BigInt::from(activity.fills));
+        store.add(0, key("makers"), BigInt::from(activity.makers.len() as u64));
+        store.add(0, key("blocks"), BigInt::from(1));
+    }
+}
+
+/// Maker fill size and count per token, side and price tick, bucketed into hourly windows and keyed
+/// `{window}:{token_id}:{bid|ask}:{tick}:{size|fills}` (see `ladder`); the implied ladders of
+/// `map_market_orderbooks`. Only the current and previous windows are kept.
+#[substreams::handlers::store]
+pub fn store_maker_levels(events: OrderFilledEvents, store: StoreAddBigInt) {
+    update_maker_levels(events, &store);
+}
+
+/// The body of `store_maker_levels`
+pub fn update_maker_levels<S: StoreAdd<BigInt>>(events: OrderFilledEvents, store: &S) {
+    let seconds = events.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(0);
+    let window = ladder::window_of(seconds);
+    if window >= 2 {
+        store.delete_prefix(0, &ladder::window_prefix(window - 2));
+    }
+
+    for ((token_id, side, tick), (size, fills)) in ladder::block_levels(&events) {
+        store.add(0, ladder::level_key(window, &token_id, side, tick, "size"), size);
+        store.add(0, ladder::level_key(window, &token_id, side, tick, "fills"), BigInt::from(fills));
+    }
+}
+
+/// Fills between two traders per token and direction, bucketed into hourly windows and keyed
+/// `{window}:{token_id}:{buyer}:{seller}` (see `wash`); the counterparty history of
+/// `map_wash_trade_signals`. Self-trades and fills against the exchange are not counted. Only the
+/// current and previous windows are kept.
+#[substreams::handlers::store]
+pub fn store_counterparty_pairs(fills: OrderFilledEvents, store: StoreAddBigInt) {
+    update_counterparty_pairs(fills, &store);
+}
+
+/// The body of `store_counterparty_pairs`
+pub fn update_counterparty_pairs<S: StoreAdd<BigInt>>(fills: OrderFilledEvents, store: &S) {
+    let seconds = fills.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(0);
+    let window = wash::window_of(seconds);
+    if window >= 2 {
+        store.delete_prefix(0, &wash::window_prefix(window - 2));
+    }
+
+    for fill in fills.events.iter().filter(|f| f.maker != f.taker && wash::has_counterparty(f)) {
+        let (buyer, seller) = wash::buyer_and_seller(fill);
+        store.add(fill.ordinal, wash::pair_key(window, &fill.token_id, buyer, seller), BigInt::from(1));
+    }
+}
+
+/// Store the last fill price of every token, keyed by `{token_id}`. Set at each fill's ordinal,
+/// so readers at an ordinal within the block see the price as of that fill.
+#[substreams::handlers::store]
+pub fn store_last_price(fills: OrderFilledEvents, store: StoreSetProto<TokenPrice>) {
+    update_last_price(fills, &store);
+}
+
+/// The body of `store_last_price`
+pub fn update_last_price<S: StoreSet<TokenPrice>>(fills: OrderFilledEvents, store: &S) {
+    for fill in &fills.events {
+        let price = TokenPrice {
+            token_id: fill.token_id.clone(),
+            price: utils::outcome_price(fill).to_string(),
+            block_number: fill.block_number,
+            ordinal: fill.ordinal,
+            timestamp: fill.timestamp,
+        };
+        store.set(fill.ordinal, &fill.token_id, &price);
+    }
+}
+
+/// Accumulate, per TWAP window, how long each token held each price: `price_seconds` (price scaled
+/// by 10^18 × seconds) and `seconds` per slot of 1/12 window, keyed `{window}:{slot}:{token_id}:{field}`.
+/// A price is credited when `store_last_price` replaces it. Params set the windows (see `twap::windows_from_params`).
+#[substreams::handlers::store]
+pub fn store_twap_accumulators(
+    params: String,
+    clock: Clock,
+    last_prices: Deltas<DeltaProto<TokenPrice>>,
+    store: StoreAddBigInt,
+) {
+    let windows = twap::windows_from_params(&params).unwrap_or_else(|e| panic!("{}", e));
+    update_twap_accumulators(&windows, clock, last_prices, &store);
+}
+
+/// The body of `store_twap_accumulators`
+pub fn update_twap_accumulators<S: StoreAdd<BigInt>>(
+    windows: &[u64],
+    clock: Clock,
+    last_prices: Deltas<DeltaProto<TokenPrice>>,
+    store: &S,
+) {
+    let now = clock.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(0);
+    for window in windows {
+        let expired = twap::slot_of(*window, now).checked_sub(twap::SLOTS_PER_WINDOW);
+        if let Some(slot) = expired {
+            store.delete_prefix(0, &twap::slot_prefix(*window, slot));
+        }
+    }
+
+    for delta in last_prices.deltas {
+        if delta.operation != substreams::pb::substreams::store_delta::Operation::Update {
+            continue;
+        }
+        let (old, new) = (delta.old_value, delta.new_value);
+        let from = old.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(0);
+        let to = new.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(0);
+        let price = twap::scale_price(&old.price);
+
+        for window in windows {
+            for (slot, seconds) in twap::held_intervals(*window, from, to) {
+                let key = |field: &str| twap::slot_key(*window, slot, &new.token_id, field);
+                store.add(delta.ordinal, key("price_seconds"), price.clone() * BigInt::from(seconds));
+                store.add(delta.ordinal, key("seconds"), BigInt::from(seconds));
+            }
+        }
+    }
+}
+
+/// Accumulate, per volatility window, the squared log-odds return of every fill against the fill
+/// before it (scaled by 10^18) and the number of returns, keyed
+/// `{window}:{slot}:{token_id}:{squared_returns|returns}` in slots of 1/12 window.
+/// Params set the windows (see `twap::windows_from_params`).
+#[substreams::handlers::store]
+pub fn store_volatility_accumulators(
+    params: String,
+    clock: Clock,
+    last_prices: Deltas<DeltaProto<TokenPrice>>,
+    store: StoreAddBigInt,
+) {
+    let windows = twap::windows_from_params(&params).unwrap_or_else(|e| panic!("{}", e));
+    update_volatility_accumulators(&windows, clock, last_prices, &store);
+}
+
+/// The body of `store_volatility_accumulators`
+pub fn update_volatility_accumulators<S: StoreAdd<BigInt>>(
+    windows: &[u64],
+    clock: Clock,
+    last_prices: Deltas<DeltaProto<TokenPrice>>,
+    store: &S,
+) {
+    let now = clock.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(0);
+    for window in windows {
+        let expired = twap::slot_of(*window, now).checked_sub(twap::SLOTS_PER_WINDOW);
+        if let Some(slot) = expired {
+            store.delete_prefix(0, &twap::slot_prefix(*window, slot));
+        }
+    }
+
+    for delta in last_prices.deltas {
+        if delta.operation != substreams::pb::substreams::store_delta::Operation::Update {
+            continue;
+        }
+        let (old, new) = (delta.old_value, delta.new_value);
+        let squared_return = volatility::squared_return(&old.price, &new.price);
+        let seconds = new.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(now);
+
+        for window in windows {
+            let slot = twap::slot_of(*window, seconds);
+            store.add(delta.ordinal, twap::slot_key(*window, slot, &new.token_id, "squared_returns"), squared_return.clone());
+            store.add(delta.ordinal, twap::slot_key(*window, slot, &new.token_id, "returns"), BigInt::from(1));
+        }
+    }
+}
+
+/// Record which traders and tokens each v2 builder code has routed, keyed
+/// `{builder}:trader:{address}` and `{builder}:market:{token_id}` with the block first seen as value.
+/// Traders are the maker and, unless the fill is against the exchange, the taker.
+/// Creates in its deltas mark new unique traders and markets for `store_builders`.
+#[substreams::handlers::store]
+pub fn store_builder_members(fills: OrderFilledEvents, store: StoreSetString) {
+    update_builder_members(fills, &store);
+}
+
+/// The body of `store_builder_members`
+pub fn update_builder_members<S: StoreSet<String>>(fills: OrderFilledEvents, store: &S) {
+    for fill in &fills.events {
+        let Some(builder) = builders::builder_code(fill) else {
+            continue;
+        };
+        let block = fill.block_number.to_string();
+        store.set(fill.ordinal, builders::trader_key(builder, &fill.maker), &block);
+        if fill.taker != fill.call_address {
+            store.set(fill.ordinal, builders::trader_key(builder, &fill.taker), &block);
+        }
+        store.set(fill.ordinal, builders::market_key(builder, &fill.token_id), &block);
+    }
+}
+
+/// Per-builder trade count, raw collateral volume, raw fees and unique trader and market counts,
+/// keyed `builder:{builder}:{trades|volume|fees|traders|markets}`
+#[substreams::handlers::store]
+pub fn store_builders(fills: OrderFilledEvents, members: Deltas<DeltaString>, store: StoreAddBigInt) {
+    update_builders(fills, members, &store);
+}
+
+/// The body of `store_builders`
+pub fn update_builders<S: StoreAdd<BigInt>, D: Delta>(fills: OrderFilledEvents, members: Deltas<D>, store: &S) {
+    for (builder, (trades, volume, fees)) in builders::block_totals(&fills) {
+        store.add(0, builders::metric_key(&builder, "trades"), BigInt::from(trades));
+        store.add(0, builders::metric_key(&builder, "volume"), volume);
+        store.add(0, builders::metric_key(&builder, "fees"), fees);
+    }
+
+    for delta in &members.deltas {
+        if delta.get_operation() != substreams::pb::substreams::store_delta::Operation::Create {
+            continue;
+        }
+        let Some((builder, kind)) = builders::parse_member_key(delta.get_key()) else {
+            continue;
+        };
+        let metric = if kind == "trader" { "traders" } else { "markets" };
+        store.add(0, builders::metric_key(builder, metric), BigInt::from(1));
+    }
+}
+
+/// Running count of v2 fills per metadata layout (`layout:{layout}`) and of fills with unknown
+/// metadata per builder code (`unknown:{builder}`)
+#[substreams::handlers::store]
+pub fn store_metadata_layouts(fills: OrderFilledEvents, store: StoreAddBigInt) {
+    update_metadata_layouts(fills, &store);
+}
+
+/// The body of `store_metadata_layouts`
+pub fn update_metadata_layouts<S: StoreAdd<BigInt>>(fills: OrderFilledEvents, store: &S) {
+    let (layouts, unknown) = metadata::block_layouts(&fills);
+    for (layout, count) in layouts {
+        store.add(0, metadata::layout_key(&layout), BigInt::from(count));
+    }
+    for (builder, (count, _)) in unknown {
+        store.add(0, metadata::unknown_key(&builder), BigInt::from(count));
+    }
+}
+
+/// Store neg-risk questions keyed `condition:{condition_id}`, and the latest question of each
+/// market keyed `market:{market_id}`. Questions are prepared in index order, so the latest
+/// question's index + 1 is the market's outcome count.
+#[substreams::handlers::store]
+pub fn store_neg_risk_questions(questions: NegRiskQuestions, store: StoreSetProto<NegRiskQuestion>) {
+    update_neg_risk_questions(questions, &store);
+}
+
+/// The body of `store_neg_risk_questions`
+pub fn update_neg_risk_questions<S: StoreSet<NegRiskQuestion>>(questions: NegRiskQuestions, store: &S) {
+    for question in questions.questions {
+        store.set(question.ordinal, format!("condition:{}", question.condition_id), &question);
+        store.set(question.ordinal, format!("market:{}", question.market_id), &question);
+    }
+}
+
+/// Store every token registered on an exchange or derived from a neg-risk question, keyed
+/// `token:{token_id}`, with its complement, condition and YES/NO outcome (see
+/// `neg_risk::registered_tokens`). Params are the contract registry of `map_exchange_events`.
+#[substreams::handlers::store]
+pub fn store_registered_tokens(
+    params: String,
+    events: ExchangeEvents,
+    questions: NegRiskQuestions,
+    store: StoreSetProto<RegisteredToken>,
+) {
+    let registry = registry::ContractRegistry::from_params(&params).unwrap_or_else(|e| panic!("{}", e));
+    update_registered_tokens(&registry, events, questions, &store);
+}
+
+/// The body of `store_registered_tokens`
+pub fn update_registered_tokens<S: StoreSet<RegisteredToken>>(
+    registry: &registry::ContractRegistry,
+    events: ExchangeEvents,
+    questions: NegRiskQuestions,
+    store: &S,
+) {
+    for token in neg_risk::registered_tokens(registry, &events, &questions) {
+        store.set(token.ordinal, format!("token:{}", token.token_id), &token);
+    }
+}
+
+/// Store the last YES price of each neg-risk outcome, keyed `price:{market_id}:{index}`.
+/// Neg Risk exchange fills resolve to an outcome through their registered token's condition.
+#[substreams::handlers::store]
+pub fn store_neg_risk_prices(
+    fills: OrderFilledEvents,
+    tokens: StoreGetProto<RegisteredToken>,
+    questions: StoreGetProto<NegRiskQuestion>,
+    store: StoreSetProto<OutcomePrice>,
+) {
+    update_neg_risk_prices(
+        fills,
+        |token_id| tokens.get_last(format!("token:{}", token_id)),
+        |condition_id| questions.get_last(format!("condition:{}", condition_id)),
+        &store,
+    );
+}
+
+/// The body of `store_neg_risk_prices`; `token` reads `store_registered_tokens` by token ID and
+/// `question` reads `store_neg_risk_questions` by condition ID
+pub fn update_neg_risk_prices<S: StoreSet<OutcomePrice>>(
+    fills: OrderFilledEvents,
+    token: impl Fn(&str) -> Option<RegisteredToken>,
+    question: impl Fn(&str) -> Option<NegRiskQuestion>,
+    store: &S,
+) {
+    for fill in fills.events.iter().filter(|f| f.exchange == "neg_risk") {
+        let Some(registered) = token(&fill.token_id) else {
+            continue;
+        };
+        let Some(question) = question(&registered.condition_id) else {
+            continue;
+        };
+
+        let price = OutcomePrice {
+            market_id: question.market_id.clone(),
+            index: question.index,
+            condition_id: question.condition_id,
+            token_id: fill.token_id.clone(),
+            yes_price: neg_risk::yes_price(fill, &registered).to_string(),
+            block_number: fill.block_number,
+            ordinal: fill.ordinal,
+        };
+        store.set(fill.ordinal, format!("price:{}:{}", question.market_id, question.index), &price);
+    }
+}
+
+/// Store the last traded price of each side of a binary condition, keyed
+/// `{condition_id}:yes` / `{condition_id}:no`, for tokens known to `store_registered_tokens`
+#[substreams::handlers::store]
+pub fn store_condition_token_prices(
+    fills: OrderFilledEvents,
+    tokens: StoreGetProto<RegisteredToken>,
+    store: StoreSetProto<TokenPrice>,
+) {
+    update_condition_token_prices(fills, |token_id| tokens.get_last(format!("token:{}", token_id)), &store);
+}
+
+/// The body of `store_condition_token_prices`; `token` reads `store_registered_tokens` by token ID
+pub fn update_condition_token_prices<S: StoreSet<TokenPrice>>(
+    fills: OrderFilledEvents,
+    token: impl Fn(&str) -> Option<RegisteredToken>,
+    store: &S,
+) {
+    for fill in &fills.events {
+        let Some(registered) = token(&fill.token_id) else {
+            continue;
+        };
+
+        let price = TokenPrice {
+            token_id: fill.token_id.clone(),
+            price: utils::outcome_price(fill).to_string(),
+            block_number: fill.block_number,
+            ordinal: fill.ordinal,
+            timestamp: fill.timestamp,
+        };
+        store.set(fill.ordinal, format!("{}:{}", registered.condition_id, registered.outcome), &price);
+    }
+}
+
 // ============================================
 // Analytics Outputs (Layer 3)
 // ============================================
 
-/// Emit market orderbook updates when markets change
+/// Emit one orderbook update per market changed in the block, with bid and ask levels, best bid and ask,
+/// spread and depth implied by the last one to two hours of maker fills, the realized volatility
+/// over the first window in params (see `store_volatility_accumulators`), and the wash-adjusted volume
 #[substreams::handlers::map]
 pub fn map_market_orderbooks(
+    params: String,
     events: OrderFilledEvents,
     store_deltas: Deltas<DeltaProto<MarketOrderbook>>,
+    maker_levels: StoreGetBigInt,
+    volatility_accumulators: StoreGetBigInt,
+    wash_adjusted_volume: StoreGetBigInt,
 ) -> Result<MarketOrderbooks, substreams::errors::Error> {
-    let orderbooks: Vec<MarketOrderbook> = store_deltas
-        .deltas
+    let volatility_window = twap::windows_from_params(&params)?[0];
+    Ok(market_orderbooks(
+        events,
+        store_deltas,
+        |key| maker_levels.get_last(key),
+        volatility_window,
+        |key| volatility_accumulators.get_last(key),
+        |key| wash_adjusted_volume.get_last(key),
+    ))
+}
+
+/// The body of `map_market_orderbooks`; `maker_levels`, `volatility_accumulators` and
+/// `wash_adjusted_volume` read `store_maker_levels`, `store_volatility_accumulators` and
+/// `store_wash_adjusted_volume` by key
+pub fn market_orderbooks(
+    events: OrderFilledEvents,
+    store_deltas: Deltas<DeltaProto<MarketOrderbook>>,
+    maker_levels: impl Fn(&str) -> Option<BigInt>,
+    volatility_window: u64,
+    volatility_accumulators: impl Fn(&str) -> Option<BigInt>,
+    wash_adjusted_volume: impl Fn(&str) -> Option<BigInt>,
+) -> MarketOrderbooks {
+    let now = events.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(0);
+
+    // store_markets sets a market once per fill; only its last value in the block is emitted
+    let mut latest: Vec<MarketOrderbook> = vec![];
+    let mut positions: BTreeMap<String, usize> = BTreeMap::new();
+    for delta in store_deltas.deltas {
+        match positions.get(&delta.key) {
+            Some(&position) => latest[position] = delta.new_value,
+            None => {
+                positions.insert(delta.key, latest.len());
+                latest.push(delta.new_value);
+            }
+        }
+    }
+
+    let orderbooks: Vec<MarketOrderbook> = latest
         .into_iter()
-        .map(|delta| delta.new_value)
+        .map(|mut orderbook| {
+            let token_id = utils::token_id_from_market_id(&orderbook.id);
+            let (bids, asks) = ladder::ladder(token_id, now, &maker_levels);
+            let (spread, depth) = ladder::spread_and_depth(&bids, &asks);
+
+            orderbook.best_bid = bids.first().map(|l| l.price.clone()).unwrap_or_default();
+            orderbook.best_ask = asks.first().map(|l| l.price.clone()).unwrap_or_default();
+            orderbook.spread = spread.to_string();
+            orderbook.market_depth = depth.to_string();
+            orderbook.volatility = volatility::realized_volatility(volatility_window, now, token_id, &volatility_accumulators)
+                .map(|(realized, _)| realized.round(volatility::RETURN_DECIMALS as i64).to_string())
+                .unwrap_or_else(|| "0".to_string());
+            orderbook.wash_adjusted_volume = wash_adjusted_volume(token_id).unwrap_or_else(BigInt::zero).to_string();
+            orderbook.bid_levels = bids;
+            orderbook.ask_levels = asks;
+            orderbook
+        })
         .collect();
 
-    Ok(MarketOrderbooks {
+    MarketOrderbooks {
         orderbooks,
         block_number: events.block_number,
         block_hash: events.block_hash,
         timestamp: events.timestamp,
-    })
+    }
 }
 
 /// Emit trader account updates when accounts change
@@ -650,18 +830,23 @@ pub fn map_trader_accounts(
     events: OrderFilledEvents,
     store_deltas: Deltas<DeltaProto<Account>>,
 ) -> Result<Accounts, substreams::errors::Error> {
+    Ok(trader_accounts(events, store_deltas))
+}
+
+/// The body of `map_trader_accounts`
+pub fn trader_accounts(events: OrderFilledEvents, store_deltas: Deltas<DeltaProto<Account>>) -> Accounts {
     let accounts: Vec<Account> = store_deltas
         .deltas
         .into_iter()
         .map(|delta| delta.new_value)
         .collect();
 
-    Ok(Accounts {
+    Accounts {
         accounts,
         block_number: events.block_number,
         block_hash: events.block_hash,
         timestamp: events.timestamp,
-    })
+    }
 }
 
 /// Emit global statistics updates
@@ -670,8 +855,16 @@ pub fn map_global_orderbook_stats(
     events: OrderFilledEvents,
     store_deltas: Deltas<DeltaProto<GlobalOrderbookStats>>,
 ) -> Result<GlobalOrderbookStats, substreams::errors::Error> {
+    Ok(global_orderbook_stats(events, store_deltas))
+}
+
+/// The body of `map_global_orderbook_stats`
+pub fn global_orderbook_stats(
+    events: OrderFilledEvents,
+    store_deltas: Deltas<DeltaProto<GlobalOrderbookStats>>,
+) -> GlobalOrderbookStats {
     // Return the latest global stats from deltas
-    let stats = store_deltas
+    store_deltas
         .deltas
         .into_iter()
         .map(|delta| delta.new_value)
@@ -687,7 +880,7 @@ pub fn map_global_orderbook_stats(
             average_trade_size: "0".to_string(),
             unique_traders: 0,
             active_markets: 0,
-            last_updated: events.timestamp.clone(),
+            last_updated: events.timestamp,
             total_liquidity: "0".to_string(),
             market_cap: "0".to_string(),
             volume_24h: "0".to_string(),
@@ -697,26 +890,560 @@ pub fn map_global_orderbook_stats(
             average_spread: "0".to_string(),
             platform_fee_revenue: "0".to_string(),
             maker_taker_ratio: "0".to_string(),
-        });
+        })
+}
+
+/// Fill/match invariants that fail, per transaction (see `consistency::check_consistency`).
+/// Fee checks start once a `MaxFeeRateUpdated` event has been seen for the exchange.
+#[substreams::handlers::map]
+pub fn map_consistency_checks(
+    clock: Clock,
+    events: ExchangeEvents,
+    max_fee_rates: StoreGetBigInt,
+) -> Result<ConsistencyViolations, substreams::errors::Error> {
+    Ok(consistency_checks(clock, events, |key| max_fee_rates.get_last(key)))
+}
+
+/// The body of `map_consistency_checks`; `max_fee_rate` reads `store_max_fee_rates`
+pub fn consistency_checks(
+    clock: Clock,
+    events: ExchangeEvents,
+    max_fee_rate: impl Fn(&str) -> Option<BigInt>,
+) -> ConsistencyViolations {
+    let violations = consistency::check_consistency(&events, |exchange| {
+        max_fee_rate(&format!("max_fee_rate:{}", exchange)).and_then(|rate| BigDecimal::from_str(&rate.to_string()).ok())
+    });
+
+    ConsistencyViolations {
+        violations,
+        block_number: clock.number,
+        block_hash: clock.id,
+        timestamp: clock.timestamp,
+    }
+}
+
+/// Alerts for tokens whose activity in this block breaks from the previous hour's baseline:
+/// volume spikes, price moves away from VWAP, bursts of makers filled and outsized single fills.
+/// Params set the thresholds (see `alerts::AlertThresholds`); empty params use the defaults.
+#[substreams::handlers::map]
+pub fn map_market_alerts(
+    params: String,
+    fills: OrderFilledEvents,
+    activity: StoreGetBigInt,
+) -> Result<MarketAlerts, substreams::errors::Error> {
+    let thresholds = alerts::AlertThresholds::parse(&params)?;
+    Ok(market_alerts(&thresholds, fills, |key| activity.get_last(key)))
+}
+
+/// The body of `map_market_alerts`; `activity` reads `store_market_activity`
+pub fn market_alerts(
+    thresholds: &alerts::AlertThresholds,
+    fills: OrderFilledEvents,
+    activity: impl Fn(&str) -> Option<BigInt>,
+) -> MarketAlerts {
+    MarketAlerts {
+        alerts: alerts::market_alerts(thresholds, &fills, activity),
+        block_number: fills.block_number,
+        block_hash: fills.block_hash,
+        timestamp: fills.timestamp,
+    }
+}
+
+/// Metadata layouts decoded in this block and unknown metadata per builder code, with running totals
+#[substreams::handlers::map]
+pub fn map_metadata_layouts(fills: OrderFilledEvents, store: StoreGetBigInt) -> Result<MetadataLayouts, substreams::errors::Error> {
+    Ok(metadata_layouts(fills, |key| store.get_last(key)))
+}
+
+/// The body of `map_metadata_layouts`; `total` reads `store_metadata_layouts` by key
+pub fn metadata_layouts(fills: OrderFilledEvents, total: impl Fn(&str) -> Option<BigInt>) -> MetadataLayouts {
+    let (layouts, unknown) = metadata::block_layouts(&fills);
+    let total_count = |key: String| total(&key).map(|t| t.to_u64()).unwrap_or(0);
+
+    MetadataLayouts {
+        layouts: layouts
+            .into_iter()
+            .map(|(layout, block_count)| MetadataLayoutCount {
+                total_count: total_count(metadata::layout_key(&layout)),
+                layout,
+                block_count,
+            })
+            .collect(),
+        unknown: unknown
+            .into_iter()
+            .map(|(builder, (block_count, sample))| UnknownMetadata {
+                total_count: total_count(metadata::unknown_key(&builder)),
+                builder,
+                block_count,
+                sample,
+            })
+            .collect(),
+        block_number: fills.block_number,
+        block_hash: fills.block_hash,
+        timestamp: fills.timestamp,
+    }
+}
+
+/// Fills of this block that look like wash trades: self-trades (maker == taker), and fills between
+/// two addresses that traded the token both ways at least `min_round_trips` times each in the
+/// current and previous hour. Params set the threshold (see `wash::WashParams`).
+#[substreams::handlers::map]
+pub fn map_wash_trade_signals(
+    params: String,
+    fills: OrderFilledEvents,
+    counterparty_pairs: StoreGetBigInt,
+) -> Result<WashTradeSignals, substreams::errors::Error> {
+    let wash_params = wash::WashParams::parse(&params)?;
+    Ok(wash_trade_signals(&wash_params, fills, |key| counterparty_pairs.get_last(key)))
+}
+
+/// The body of `map_wash_trade_signals`; `pair_count` reads `store_counterparty_pairs` by key
+pub fn wash_trade_signals(
+    wash_params: &wash::WashParams,
+    fills: OrderFilledEvents,
+    pair_count: impl Fn(&str) -> Option<BigInt>,
+) -> WashTradeSignals {
+    let now = fills.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(0);
+    let signals = wash::flag_fills(wash_params, &fills.events, now, pair_count)
+        .into_iter()
+        .map(|flagged| {
+            let (forward_fills, reverse_fills) = match flagged.signal {
+                wash::WashSignal::Circular { forward, reverse } => (forward, reverse),
+                wash::WashSignal::SelfTrade => (0, 0),
+            };
+            WashTradeSignal {
+                fill_id: flagged.fill.id.clone(),
+                transaction_hash: flagged.fill.transaction_hash.clone(),
+                token_id: flagged.fill.token_id.clone(),
+                kind: flagged.signal.kind().to_string(),
+                buyer: flagged.buyer,
+                seller: flagged.seller,
+                forward_fills,
+                reverse_fills,
+                collateral_amount: flagged.collateral.to_string(),
+                block_number: fills.block_number,
+                timestamp: fills.timestamp,
+            }
+        })
+        .collect();
+
+    WashTradeSignals {
+        signals,
+        block_number: fills.block_number,
+        block_hash: fills.block_hash,
+        timestamp: fills.timestamp,
+    }
+}
 
-    Ok(stats)
+/// Running collateral volume of every token less the collateral of fills `map_wash_trade_signals`
+/// flagged, keyed `{token_id}`
+#[substreams::handlers::store]
+pub fn store_wash_adjusted_volume(fills: OrderFilledEvents, signals: WashTradeSignals, store: StoreAddBigInt) {
+    update_wash_adjusted_volume(fills, signals, &store);
+}
+
+/// The body of `store_wash_adjusted_volume`
+pub fn update_wash_adjusted_volume<S: StoreAdd<BigInt>>(fills: OrderFilledEvents, signals: WashTradeSignals, store: &S) {
+    let mut washed: BTreeMap<&str, BigInt> = BTreeMap::new();
+    for signal in &signals.signals {
+        let collateral = BigInt::from_str(&signal.collateral_amount).unwrap_or_else(|_| BigInt::zero());
+        *washed.entry(signal.fill_id.as_str()).or_insert_with(BigInt::zero) += collateral;
+    }
+
+    for fill in &fills.events {
+        let collateral = BigInt::from_str(utils::collateral_amount(fill)).unwrap_or_else(|_| BigInt::zero());
+        let adjusted = match washed.remove(fill.id.as_str()) {
+            Some(washed) if washed >= collateral => BigInt::zero(),
+            Some(washed) => collateral - washed,
+            None => collateral,
+        };
+        store.add(fill.ordinal, &fill.token_id, adjusted);
+    }
+}
+
+/// Totals of every builder code that routed a fill in this block
+#[substreams::handlers::map]
+pub fn map_builder_stats(fills: OrderFilledEvents, store: StoreGetBigInt) -> Result<Builders, substreams::errors::Error> {
+    Ok(builder_stats(fills, |key| store.get_last(key)))
+}
+
+/// The body of `map_builder_stats`; `metric` reads `store_builders` by key
+pub fn builder_stats(fills: OrderFilledEvents, metric: impl Fn(&str) -> Option<BigInt>) -> Builders {
+    let builders = builders::block_totals(&fills)
+        .into_keys()
+        .map(|builder| {
+            let value = |name: &str| metric(&builders::metric_key(&builder, name)).unwrap_or_else(BigInt::zero);
+            Builder {
+                trades_quantity: value("trades").to_u64(),
+                collateral_volume: value("volume").to_string(),
+                total_fees: value("fees").to_string(),
+                unique_traders: value("traders").to_u64(),
+                unique_markets: value("markets").to_u64(),
+                last_active_block: fills.block_number,
+                id: builder,
+            }
+        })
+        .collect();
+
+    Builders {
+        builders,
+        block_number: fills.block_number,
+        block_hash: fills.block_hash,
+        timestamp: fills.timestamp,
+    }
+}
+
+/// Realized volatility over each configured window for every token traded in this block.
+/// Params must match those given to `store_volatility_accumulators`.
+#[substreams::handlers::map]
+pub fn map_token_volatility(
+    params: String,
+    fills: OrderFilledEvents,
+    accumulators: StoreGetBigInt,
+) -> Result<TokenVolatilities, substreams::errors::Error> {
+    let windows = twap::windows_from_params(&params)?;
+    Ok(token_volatility(&windows, fills, |key| accumulators.get_last(key)))
+}
+
+/// The body of `map_token_volatility`; `accumulator` reads `store_volatility_accumulators` by key
+pub fn token_volatility(windows: &[u64], fills: OrderFilledEvents, accumulator: impl Fn(&str) -> Option<BigInt>) -> TokenVolatilities {
+    let now = fills.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(0);
+    let mut tokens: Vec<&str> = fills.events.iter().map(|f| f.token_id.as_str()).collect();
+    tokens.sort();
+    tokens.dedup();
+
+    let mut volatilities = vec![];
+    for token_id in tokens {
+        for window in windows {
+            let Some((realized, returns)) = volatility::realized_volatility(*window, now, token_id, &accumulator) else {
+                continue;
+            };
+            volatilities.push(TokenVolatility {
+                token_id: token_id.to_string(),
+                window_seconds: *window,
+                volatility: realized.round(volatility::RETURN_DECIMALS as i64).to_string(),
+                returns,
+                block_number: fills.block_number,
+                timestamp: fills.timestamp,
+            });
+        }
+    }
+
+    TokenVolatilities {
+        volatilities,
+        block_number: fills.block_number,
+        block_hash: fills.block_hash,
+        timestamp: fills.timestamp,
+    }
+}
+
+/// Time-weighted average price over each configured window for every token traded in this block.
+/// Params must match those given to `store_twap_accumulators`.
+#[substreams::handlers::map]
+pub fn map_twap_prices(
+    params: String,
+    fills: OrderFilledEvents,
+    last_prices: StoreGetProto<TokenPrice>,
+    accumulators: StoreGetBigInt,
+) -> Result<TwapPrices, substreams::errors::Error> {
+    let windows = twap::windows_from_params(&params)?;
+    Ok(twap_prices(&windows, fills, |key| last_prices.get_last(key), |key| accumulators.get_last(key)))
+}
+
+/// The body of `map_twap_prices`; `last_price` and `accumulator` read `store_last_price` and
+/// `store_twap_accumulators` by key
+pub fn twap_prices(
+    windows: &[u64],
+    fills: OrderFilledEvents,
+    last_price: impl Fn(&str) -> Option<TokenPrice>,
+    accumulator: impl Fn(&str) -> Option<BigInt>,
+) -> TwapPrices {
+    let now = fills.timestamp.as_ref().map(|t| t.seconds as u64).unwrap_or(0);
+    let mut tokens: Vec<&str> = fills.events.iter().map(|f| f.token_id.as_str()).collect();
+    tokens.sort();
+    tokens.dedup();
+
+    let mut prices = vec![];
+    for token_id in tokens {
+        let last = last_price(token_id);
+        for window in windows {
+            let Some((average, covered_seconds)) = twap::twap(*window, now, token_id, last.as_ref(), &accumulator) else {
+                continue;
+            };
+            prices.push(TwapPrice {
+                token_id: token_id.to_string(),
+                window_seconds: *window,
+                twap: average.round(twap::PRICE_DECIMALS as i64).to_string(),
+                last_price: last.as_ref().map(|l| l.price.clone()).unwrap_or_default(),
+                covered_seconds,
+                block_number: fills.block_number,
+                timestamp: fills.timestamp,
+            });
+        }
+    }
+
+    TwapPrices {
+        prices,
+        block_number: fills.block_number,
+        block_hash: fills.block_hash,
+        timestamp: fills.timestamp,
+    }
+}
+
+/// Implied YES probability, YES + NO price sum and its deviation from 1 for every condition
+/// traded in this block (see `conditions::condition_price`)
+#[substreams::handlers::map]
+pub fn map_condition_prices(
+    fills: OrderFilledEvents,
+    tokens: StoreGetProto<RegisteredToken>,
+    prices: StoreGetProto<TokenPrice>,
+) -> Result<ConditionPrices, substreams::errors::Error> {
+    Ok(condition_prices(fills, |key| tokens.get_last(key), |key| prices.get_last(key)))
+}
+
+/// The body of `map_condition_prices`; `token` and `price` read `store_registered_tokens` and
+/// `store_condition_token_prices` by key
+pub fn condition_prices(
+    fills: OrderFilledEvents,
+    token: impl Fn(&str) -> Option<RegisteredToken>,
+    price: impl Fn(&str) -> Option<TokenPrice>,
+) -> ConditionPrices {
+    let mut traded: BTreeMap<String, RegisteredToken> = BTreeMap::new();
+    for fill in &fills.events {
+        if let Some(registered) = token(&format!("token:{}", fill.token_id)) {
+            traded.entry(registered.condition_id.clone()).or_insert(registered);
+        }
+    }
+
+    let prices = traded
+        .into_iter()
+        .map(|(condition_id, registered)| {
+            conditions::condition_price(
+                &registered,
+                price(&format!("{}:yes", condition_id)),
+                price(&format!("{}:no", condition_id)),
+                fills.block_number,
+                fills.timestamp,
+            )
+        })
+        .collect();
+
+    ConditionPrices {
+        prices,
+        block_number: fills.block_number,
+        block_hash: fills.block_hash,
+        timestamp: fills.timestamp,
+    }
+}
+
+/// Neg-risk markets traded in this block whose outcome YES prices sum away from 1 by at least
+/// `min_deviation`. Params: `min_deviation:0.02 max_age_blocks:1800` (see `neg_risk::ArbitrageParams`).
+#[substreams::handlers::map]
+pub fn map_neg_risk_arbitrage(
+    params: String,
+    fills: OrderFilledEvents,
+    tokens: StoreGetProto<RegisteredToken>,
+    questions: StoreGetProto<NegRiskQuestion>,
+    prices: StoreGetProto<OutcomePrice>,
+) -> Result<TradingOpportunities, substreams::errors::Error> {
+    let params = neg_risk::ArbitrageParams::parse(&params)?;
+    Ok(neg_risk_arbitrage(
+        &params,
+        fills,
+        |key| tokens.get_last(key),
+        |key| questions.get_last(key),
+        |key| prices.get_last(key),
+    ))
+}
+
+/// The body of `map_neg_risk_arbitrage`; `token`, `question` and `price` read
+/// `store_registered_tokens`, `store_neg_risk_questions` and `store_neg_risk_prices` by key
+pub fn neg_risk_arbitrage(
+    params: &neg_risk::ArbitrageParams,
+    fills: OrderFilledEvents,
+    token: impl Fn(&str) -> Option<RegisteredToken>,
+    question: impl Fn(&str) -> Option<NegRiskQuestion>,
+    price: impl Fn(&str) -> Option<OutcomePrice>,
+) -> TradingOpportunities {
+    let mut markets: Vec<String> = fills
+        .events
+        .iter()
+        .filter(|f| f.exchange == "neg_risk")
+        .filter_map(|f| token(&format!("token:{}", f.token_id)))
+        .filter_map(|t| question(&format!("condition:{}", t.condition_id)))
+        .map(|q| q.market_id)
+        .collect();
+    markets.sort();
+    markets.dedup();
+
+    let opportunities = markets
+        .iter()
+        .filter_map(|market_id| {
+            let latest = question(&format!("market:{}", market_id))?;
+            let outcomes = (0..=latest.index)
+                .map(|index| price(&format!("price:{}:{}", market_id, index)))
+                .collect();
+            neg_risk::arbitrage_opportunity(params, market_id, outcomes, fills.block_number, fills.timestamp)
+        })
+        .collect();
+
+    TradingOpportunities {
+        opportunities,
+        block_number: fills.block_number,
+        block_hash: fills.block_hash,
+        timestamp: fills.timestamp,
+    }
 }
 
 // ============================================
 // Database Sink Output (Layer 4)
 // ============================================
 
-/// Converts all analytics outputs into DatabaseChanges for substreams-sink-sql
+/// Converts all analytics outputs into DatabaseChanges for substreams-sink-sql.
+///
+/// Every row carries the `block_number`/`block_hash` it was produced at. Aggregates are written
+/// twice: an upsert into the latest-state table keyed by `id`, and an insert into a `*_snapshots`
+/// table keyed by `(id, block_number)`, so a reorg can be rolled back either by the sink's undo
+/// handling or by deleting snapshot rows above the fork block.
 #[substreams::handlers::map]
 pub fn db_out(
+    clock: Clock,
     order_fills: OrderFilledEvents,
     market_orderbooks: MarketOrderbooks,
     trader_accounts: Accounts,
     global_stats: GlobalOrderbookStats,
+    consistency_violations: ConsistencyViolations,
+    builder_stats: Builders,
 ) -> Result<DatabaseChanges, substreams::errors::Error> {
     let mut tables = Tables::new();
+    let block_number = order_fills.block_number.to_string();
+    let block_hash = order_fills.block_hash.clone();
+    let block_timestamp = block_timestamp(&clock);
 
     // Order fills → order_fills table (CREATE for each event)
+    add_order_fill_rows(&mut tables, &order_fills, &block_timestamp);
+
+    // Failed invariants → consistency_violations table (CREATE for each violation)
+    add_consistency_violation_rows(&mut tables, &consistency_violations, &block_timestamp);
+
+    // Builder codes → builder_stats (UPSERT latest)
+    for builder in &builder_stats.builders {
+        set_builder_columns(tables.upsert_row("builder_stats", &builder.id), builder)
+            .set("block_number", &block_number)
+            .set("block_hash", &block_hash);
+    }
+
+    // Market orderbooks → market_orderbooks (UPSERT latest) + market_orderbook_snapshots (per block)
+    for orderbook in last_per_id(&market_orderbooks.orderbooks, |o| &o.id).values() {
+        set_market_orderbook_columns(tables.upsert_row("market_orderbooks", &orderbook.id), orderbook)
+            .set("block_number", &block_number)
+            .set("block_hash", &block_hash);
+        set_market_orderbook_columns(
+            tables.create_row(
+                "market_orderbook_snapshots",
+                [("id", orderbook.id.clone()), ("block_number", block_number.clone())],
+            ),
+            orderbook,
+        )
+        .set("block_hash", &block_hash);
+    }
+
+    // Trader accounts → trader_accounts (UPSERT latest) + trader_account_snapshots (per block)
+    for account in last_per_id(&trader_accounts.accounts, |a| &a.id).values() {
+        set_trader_account_columns(tables.upsert_row("trader_accounts", &account.id), account)
+            .set("block_number", &block_number)
+            .set("block_hash", &block_hash);
+        set_trader_account_columns(
+            tables.create_row(
+                "trader_account_snapshots",
+                [("id", account.id.clone()), ("block_number", block_number.clone())],
+            ),
+            account,
+        )
+        .set("block_hash", &block_hash);
+    }
+
+    // Global stats → global_stats (UPSERT single row) + global_stats_snapshots (per block).
+    // store_global_stats only writes on blocks with fills; skip the zeroed fallback otherwise.
+    if !order_fills.events.is_empty() {
+        set_global_stats_columns(tables.upsert_row("global_stats", &global_stats.id), &global_stats)
+            .set("block_number", &block_number)
+            .set("block_hash", &block_hash);
+        set_global_stats_columns(
+            tables.create_row(
+                "global_stats_snapshots",
+                [("id", global_stats.id.clone()), ("block_number", block_number.clone())],
+            ),
+            &global_stats,
+        )
+        .set("block_hash", &block_hash);
+    }
+
+    Ok(tables.to_database_changes())
+}
+
+fn set_market_orderbook_columns<'r>(row: &'r mut Row, orderbook: &MarketOrderbook) -> &'r mut Row {
+    row.set("condition_id", &orderbook.condition_id)
+        .set("trades_quantity", orderbook.trades_quantity.to_string())
+        .set("buys_quantity", orderbook.buys_quantity.to_string())
+        .set("sells_quantity", orderbook.sells_quantity.to_string())
+        .set("collateral_volume", &orderbook.collateral_volume)
+        .set("wash_adjusted_volume", &orderbook.wash_adjusted_volume)
+        .set("average_trade_size", &orderbook.average_trade_size)
+        .set("total_fees", &orderbook.total_fees)
+        .set("mid_price", &orderbook.mid_price)
+        .set("spread", &orderbook.spread)
+        .set("market_depth", &orderbook.market_depth)
+        .set("volatility", &orderbook.volatility)
+        .set("last_updated_block", orderbook.last_updated_block.to_string());
+    set_best_prices(row, orderbook)
+}
+
+/// Best bid and ask columns are left NULL while a side has no implied levels
+fn set_best_prices<'r>(row: &'r mut Row, orderbook: &MarketOrderbook) -> &'r mut Row {
+    if !orderbook.best_bid.is_empty() {
+        row.set("best_bid", &orderbook.best_bid);
+    }
+    if !orderbook.best_ask.is_empty() {
+        row.set("best_ask", &orderbook.best_ask);
+    }
+    row
+}
+
+fn set_builder_columns<'r>(row: &'r mut Row, builder: &Builder) -> &'r mut Row {
+    row.set("trades_quantity", builder.trades_quantity.to_string())
+        .set("collateral_volume", &builder.collateral_volume)
+        .set("total_fees", &builder.total_fees)
+        .set("unique_traders", builder.unique_traders.to_string())
+        .set("unique_markets", builder.unique_markets.to_string())
+        .set("last_active_block", builder.last_active_block.to_string())
+}
+
+fn set_trader_account_columns<'r>(row: &'r mut Row, account: &Account) -> &'r mut Row {
+    row.set("trades_quantity", account.trades_quantity.to_string())
+        .set("total_volume", &account.total_volume)
+        .set("total_fees", &account.total_fees)
+        .set("is_active", account.is_active.to_string())
+        .set("trader_type", &account.trader_type)
+}
+
+fn set_global_stats_columns<'r>(row: &'r mut Row, global_stats: &GlobalOrderbookStats) -> &'r mut Row {
+    row.set("trades_quantity", global_stats.trades_quantity.to_string())
+        .set("buys_quantity", global_stats.buys_quantity.to_string())
+        .set("sells_quantity", global_stats.sells_quantity.to_string())
+        .set("collateral_volume", &global_stats.collateral_volume)
+        .set("total_fees", &global_stats.total_fees)
+        .set("average_trade_size", &global_stats.average_trade_size)
+        .set("platform_fee_revenue", &global_stats.platform_fee_revenue)
+}
+
+/// The clock's block time as a `block_timestamp` column value
+fn block_timestamp(clock: &Clock) -> String {
+    utils::sql_timestamp(clock.timestamp.as_ref().map(|t| t.seconds).unwrap_or(0))
+}
+
+/// Appends one `order_fills` row per fill. Shared by the Postgres and ClickHouse outputs.
+fn add_order_fill_rows(tables: &mut Tables, order_fills: &OrderFilledEvents, block_timestamp: &str) {
     for event in &order_fills.events {
         tables
             .create_row("order_fills", &event.id)
@@ -732,57 +1459,338 @@ pub fn db_out(
             .set("side", &event.side)
             .set("price", &event.price)
             .set("block_number", event.block_number.to_string())
+            .set("block_hash", &order_fills.block_hash)
+            .set("block_timestamp", block_timestamp)
+            .set("log_index", event.log_index.to_string())
+            .set("ordinal", event.ordinal.to_string())
             .set("exchange_version", &event.exchange_version)
+            .set("exchange", &event.exchange)
             .set("token_id", &event.token_id)
             .set("side_raw", event.side_raw.to_string())
             .set("builder", &event.builder)
-            .set("metadata", &event.metadata);
+            .set("metadata", &event.metadata)
+            .set("metadata_layout", &event.metadata_layout)
+            .set("metadata_value", event.metadata_fields.first().map(|f| f.value.as_str()).unwrap_or_default())
+            .set("tx_from", &event.tx_from)
+            .set("tx_to", &event.tx_to)
+            .set("tx_index", event.tx_index.to_string())
+            .set("gas_used", event.gas_used.to_string())
+            .set("effective_gas_price", &event.effective_gas_price)
+            .set("call_address", &event.call_address);
     }
+}
 
-    // Market orderbooks → market_orderbooks table (UPSERT for aggregated data)
-    for orderbook in &market_orderbooks.orderbooks {
+fn add_consistency_violation_rows(tables: &mut Tables, violations: &ConsistencyViolations, block_timestamp: &str) {
+    for violation in &violations.violations {
         tables
-            .update_row("market_orderbooks", &orderbook.id)
+            .create_row("consistency_violations", &violation.id)
+            .set("transaction_hash", &violation.transaction_hash)
+            .set("block_number", violation.block_number.to_string())
+            .set("block_hash", &violations.block_hash)
+            .set("block_timestamp", block_timestamp)
+            .set("ordinal", violation.ordinal.to_string())
+            .set("exchange", &violation.exchange)
+            .set("exchange_version", &violation.exchange_version)
+            .set("check_name", &violation.check)
+            .set("order_hash", &violation.order_hash)
+            .set("expected", &violation.expected)
+            .set("actual", &violation.actual);
+    }
+}
+
+// ============================================
+// ClickHouse Sink Output (Layer 4)
+// ============================================
+
+/// Converts analytics outputs into append-only DatabaseChanges matching `clickhouse-schema.sql`.
+/// Every analytics row is inserted with a `version` column (the block number) so the
+/// `ReplacingMergeTree(version)` tables keep the latest state per `id` after merges.
+#[substreams::handlers::map]
+pub fn db_out_clickhouse(
+    clock: Clock,
+    order_fills: OrderFilledEvents,
+    market_orderbooks: MarketOrderbooks,
+    trader_accounts: Accounts,
+    global_stats: GlobalOrderbookStats,
+    consistency_violations: ConsistencyViolations,
+    builder_stats: Builders,
+) -> Result<DatabaseChanges, substreams::errors::Error> {
+    let mut tables = Tables::new();
+    let version = order_fills.block_number.to_string();
+    let updated_at = block_timestamp(&clock);
+
+    add_order_fill_rows(&mut tables, &order_fills, &updated_at);
+    add_consistency_violation_rows(&mut tables, &consistency_violations, &updated_at);
+
+    for builder in &builder_stats.builders {
+        set_builder_columns(tables.create_row("builder_stats", &builder.id), builder)
+            .set("block_hash", &order_fills.block_hash)
+            .set("version", &version)
+            .set("updated_at", &updated_at);
+    }
+
+    for orderbook in last_per_id(&market_orderbooks.orderbooks, |o| &o.id).values() {
+        let row = tables
+            .create_row("market_analytics", &orderbook.id)
             .set("condition_id", &orderbook.condition_id)
             .set("trades_quantity", orderbook.trades_quantity.to_string())
             .set("buys_quantity", orderbook.buys_quantity.to_string())
             .set("sells_quantity", orderbook.sells_quantity.to_string())
             .set("collateral_volume", &orderbook.collateral_volume)
+            .set("wash_adjusted_volume", &orderbook.wash_adjusted_volume)
             .set("average_trade_size", &orderbook.average_trade_size)
             .set("total_fees", &orderbook.total_fees)
             .set("mid_price", &orderbook.mid_price)
-            .set("last_updated_block", orderbook.last_updated_block.to_string());
+            .set("spread", &orderbook.spread)
+            .set("market_depth", &orderbook.market_depth)
+            .set("volatility", &orderbook.volatility)
+            .set("volume_24h", &orderbook.volume_24h)
+            .set("volume_7d", &orderbook.volume_7d)
+            .set("liquidity_score", &orderbook.liquidity_score)
+            .set("last_updated_block", orderbook.last_updated_block.to_string())
+            .set("block_hash", &order_fills.block_hash)
+            .set("version", &version)
+            .set("updated_at", &updated_at);
+        set_best_prices(row, orderbook);
     }
 
-    // Trader accounts → trader_accounts table (UPSERT for aggregated data)
-    for account in &trader_accounts.accounts {
+    for account in last_per_id(&trader_accounts.accounts, |a| &a.id).values() {
         tables
-            .update_row("trader_accounts", &account.id)
+            .create_row("trader_analytics", &account.id)
             .set("trades_quantity", account.trades_quantity.to_string())
             .set("total_volume", &account.total_volume)
             .set("total_fees", &account.total_fees)
-            .set("is_active", account.is_active.to_string())
-            .set("trader_type", &account.trader_type);
+            .set("volume_24h", &account.volume_24h)
+            .set("volume_7d", &account.volume_7d)
+            .set("markets_traded", account.markets_traded.to_string())
+            .set("is_active", if account.is_active { "1" } else { "0" })
+            .set("trader_type", &account.trader_type)
+            .set("block_hash", &order_fills.block_hash)
+            .set("version", &version)
+            .set("updated_at", &updated_at);
     }
 
-    // Global stats → global_stats table (UPSERT single row)
-    tables
-        .update_row("global_stats", &global_stats.id)
-        .set("trades_quantity", global_stats.trades_quantity.to_string())
-        .set("buys_quantity", global_stats.buys_quantity.to_string())
-        .set("sells_quantity", global_stats.sells_quantity.to_string())
-        .set("collateral_volume", &global_stats.collateral_volume)
-        .set("total_fees", &global_stats.total_fees)
-        .set("average_trade_size", &global_stats.average_trade_size)
-        .set("platform_fee_revenue", &global_stats.platform_fee_revenue);
+    // store_global_stats only writes on blocks with fills; skip the zeroed fallback row otherwise
+    if !order_fills.events.is_empty() {
+        tables
+            .create_row("global_analytics", &global_stats.id)
+            .set("trades_quantity", global_stats.trades_quantity.to_string())
+            .set("buys_quantity", global_stats.buys_quantity.to_string())
+            .set("sells_quantity", global_stats.sells_quantity.to_string())
+            .set("collateral_volume", &global_stats.collateral_volume)
+            .set("total_fees", &global_stats.total_fees)
+            .set("unique_traders", global_stats.unique_traders.to_string())
+            .set("active_markets", global_stats.active_markets.to_string())
+            .set("volume_24h", &global_stats.volume_24h)
+            .set("volume_7d", &global_stats.volume_7d)
+            .set("platform_fee_revenue", &global_stats.platform_fee_revenue)
+            .set("block_hash", &order_fills.block_hash)
+            .set("version", &version)
+            .set("updated_at", &updated_at);
+    }
 
     Ok(tables.to_database_changes())
 }
 
+// ============================================
+// Subgraph Output (Layer 4)
+// ============================================
+
+/// Converts fills and store-driven analytics into EntityChanges for a Substreams-powered subgraph.
+/// Entities match `schema.graphql`; fills are immutable, aggregates are overwritten on each change.
+#[substreams::handlers::map]
+pub fn graph_out(
+    order_fills: OrderFilledEvents,
+    market_orderbooks: MarketOrderbooks,
+    trader_accounts: Accounts,
+    global_stats: GlobalOrderbookStats,
+) -> Result<EntityChanges, substreams::errors::Error> {
+    let mut entity_changes = Vec::new();
+
+    for event in &order_fills.events {
+        entity_changes.push(
+            GraphEntity::create("OrderFill", &event.id)
+                .string("transactionHash", &event.transaction_hash)
+                .string("orderHash", &event.order_hash)
+                .string("maker", &event.maker)
+                .string("taker", &event.taker)
+                .string("makerAssetId", &event.maker_asset_id)
+                .string("takerAssetId", &event.taker_asset_id)
+                .bigint("makerAmountFilled", graph_bigint(&event.maker_amount_filled))
+                .bigint("takerAmountFilled", graph_bigint(&event.taker_amount_filled))
+                .bigint("fee", graph_bigint(&event.fee))
+                .string("side", &event.side)
+                .bigdecimal("price", graph_bigdecimal(&event.price))
+                .string("tokenId", &event.token_id)
+                .string("exchangeVersion", &event.exchange_version)
+                .string("builder", &event.builder)
+                .string("metadata", &event.metadata)
+                .string("txFrom", &event.tx_from)
+                .string("market", &utils::extract_condition_id_from_str(&event.token_id))
+                .bigint("blockNumber", event.block_number.to_string())
+                .bigint("timestamp", event.timestamp.as_ref().map(|t| t.seconds).unwrap_or(0).to_string())
+                .int("logIndex", event.log_index as i32)
+                .into(),
+        );
+    }
+
+    for orderbook in last_per_id(&market_orderbooks.orderbooks, |o| &o.id).values() {
+        entity_changes.push(
+            GraphEntity::update("Market", &orderbook.id)
+                .string("conditionId", &orderbook.condition_id)
+                .bigint("tradesQuantity", orderbook.trades_quantity.to_string())
+                .bigint("buysQuantity", orderbook.buys_quantity.to_string())
+                .bigint("sellsQuantity", orderbook.sells_quantity.to_string())
+                .bigint("collateralVolume", graph_bigint(&orderbook.collateral_volume))
+                .bigdecimal("averageTradeSize", graph_bigdecimal(&orderbook.average_trade_size))
+                .bigint("totalFees", graph_bigint(&orderbook.total_fees))
+                .bigdecimal("midPrice", graph_bigdecimal(&orderbook.mid_price))
+                .bigint("lastUpdatedBlock", orderbook.last_updated_block.to_string())
+                .into(),
+        );
+    }
+
+    for account in last_per_id(&trader_accounts.accounts, |a| &a.id).values() {
+        entity_changes.push(
+            GraphEntity::update("Account", &account.id)
+                .bigint("tradesQuantity", account.trades_quantity.to_string())
+                .bigint("totalVolume", graph_bigint(&account.total_volume))
+                .bigint("totalFees", graph_bigint(&account.total_fees))
+                .boolean("isActive", account.is_active)
+                .string("traderType", &account.trader_type)
+                .bigint("lastTrade", account.last_trade.as_ref().map(|t| t.seconds).unwrap_or(0).to_string())
+                .into(),
+        );
+    }
+
+    // store_global_stats only writes on blocks with fills; skip the zeroed fallback otherwise
+    if !order_fills.events.is_empty() {
+        entity_changes.push(
+            GraphEntity::update("GlobalStats", &global_stats.id)
+                .bigint("tradesQuantity", global_stats.trades_quantity.to_string())
+                .bigint("buysQuantity", global_stats.buys_quantity.to_string())
+                .bigint("sellsQuantity", global_stats.sells_quantity.to_string())
+                .bigint("collateralVolume", graph_bigint(&global_stats.collateral_volume))
+                .bigint("totalFees", graph_bigint(&global_stats.total_fees))
+                .bigdecimal("averageTradeSize", graph_bigdecimal(&global_stats.average_trade_size))
+                .bigint("platformFeeRevenue", graph_bigint(&global_stats.platform_fee_revenue))
+                .bigint("lastUpdatedBlock", order_fills.block_number.to_string())
+                .into(),
+        );
+    }
+
+    Ok(EntityChanges { entity_changes })
+}
+
+/// One subgraph entity change, built field by field
+struct GraphEntity(EntityChange);
+
+impl GraphEntity {
+    fn create(entity: &str, id: &str) -> Self {
+        Self::new(entity, id, entity_change::Operation::Create)
+    }
+
+    fn update(entity: &str, id: &str) -> Self {
+        Self::new(entity, id, entity_change::Operation::Update)
+    }
+
+    fn new(entity: &str, id: &str, operation: entity_change::Operation) -> Self {
+        GraphEntity(EntityChange {
+            entity: entity.to_string(),
+            id: id.to_string(),
+            operation: operation.into(),
+            ..Default::default()
+        })
+    }
+
+    fn set(mut self, name: &str, typed: value::Typed) -> Self {
+        self.0.fields.push(Field {
+            name: name.to_string(),
+            new_value: Some(Value { typed: Some(typed) }),
+            ..Default::default()
+        });
+        self
+    }
+
+    fn string(self, name: &str, value: &str) -> Self {
+        self.set(name, value::Typed::String(value.to_string()))
+    }
+
+    fn bigint(self, name: &str, value: String) -> Self {
+        self.set(name, value::Typed::Bigint(value))
+    }
+
+    fn bigdecimal(self, name: &str, value: String) -> Self {
+        self.set(name, value::Typed::Bigdecimal(value))
+    }
+
+    fn int(self, name: &str, value: i32) -> Self {
+        self.set(name, value::Typed::Int32(value))
+    }
+
+    fn boolean(self, name: &str, value: bool) -> Self {
+        self.set(name, value::Typed::Bool(value))
+    }
+}
+
+impl From<GraphEntity> for EntityChange {
+    fn from(entity: GraphEntity) -> Self {
+        entity.0
+    }
+}
+
+// ============================================
+// Key-Value Sink Output (Layer 4)
+// ============================================
+
+/// Emits the latest MarketOrderbook and Account protos as KVOperations for substreams-sink-kv.
+/// Keys are `market:{token_id}` and `trader:{address}`; values are the encoded protos.
+#[substreams::handlers::map]
+pub fn kv_out(
+    market_deltas: Deltas<DeltaProto<MarketOrderbook>>,
+    trader_deltas: Deltas<DeltaProto<Account>>,
+) -> Result<KvOperations, substreams::errors::Error> {
+    let mut operations = Vec::new();
+
+    for delta in market_deltas.deltas {
+        let orderbook = if delta.operation == Operation::Delete { &delta.old_value } else { &delta.new_value };
+        let key = format!("market:{}", utils::token_id_from_market_id(&orderbook.id));
+        operations.push(kv_operation(key, delta.operation, &delta.new_value, delta.ordinal));
+    }
+
+    for delta in trader_deltas.deltas {
+        let account = if delta.operation == Operation::Delete { &delta.old_value } else { &delta.new_value };
+        let key = format!("trader:{}", account.id);
+        operations.push(kv_operation(key, delta.operation, &delta.new_value, delta.ordinal));
+    }
+
+    Ok(KvOperations { operations })
+}
+
+/// A KV set of the encoded `value`, or a KV delete when the store key was deleted
+fn kv_operation<T: Message>(key: String, operation: Operation, value: &T, ordinal: u64) -> KvOperation {
+    let (kind, value) = match operation {
+        Operation::Delete => (kv_operation::Type::Delete, vec![]),
+        _ => (kv_operation::Type::Set, value.encode_to_vec()),
+    };
+    KvOperation {
+        key,
+        value,
+        ordinal,
+        r#type: kind.into(),
+    }
+}
+
 // ============================================
 // Helper Functions
 // ============================================
 
+/// Index rows by id, keeping the last occurrence. Store deltas can carry several updates
+/// for the same key within a block, and sinks expect one row per key per block.
+fn last_per_id<'a, T>(items: &'a [T], id: impl Fn(&'a T) -> &'a String) -> BTreeMap<&'a str, &'a T> {
+    items.iter().map(|item| (id(item).as_str(), item)).collect()
+}
+
 fn scale_amount(amount: &str) -> String {
     // Scale from wei (18 decimals) to human-readable
     if let Ok(val) = BigDecimal::from_str(amount) {
@@ -797,3 +1805,13 @@ fn scale_bigdecimal(val: &BigDecimal) -> String {
     let scaled = val / BigDecimal::from_str("1000000000000000000").unwrap();
     scaled.to_string()
 }
+
+/// A raw integer amount as a subgraph `BigInt` string, 0 if it does not parse
+fn graph_bigint(value: &str) -> String {
+    BigInt::from_str(value).unwrap_or_else(|_| BigInt::zero()).to_string()
+}
+
+/// A decimal as a subgraph `BigDecimal` string, 0 if it does not parse
+fn graph_bigdecimal(value: &str) -> String {
+    BigDecimal::from_str(value).map(|d| d.normalized().to_string()).unwrap_or_else(|_| "0".to_string())
+}
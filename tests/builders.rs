@@ -0,0 +1,70 @@
+mod common;
+
+use polymarket_orderbook_substreams::pb::polymarket::orderbook::v1::{Builder, OrderFilledEvents};
+use polymarket_orderbook_substreams::{builder_stats, builders, update_builder_members, update_builders};
+use substreams::scalar::BigInt;
+
+const BUILDER: &str = "f5273d7ebb3851b228da5cff8bdd23aa9b4b6ba443d5c308ae56fbddd4e8d47d";
+const BOB: &str = "d42f6a1634a3707e27cbae14ca966068e5d1047d";
+const CAROL: &str = "5b38da6a701c568545dcfcb03fcb875f56beddc4";
+
+#[derive(Default)]
+struct Stores {
+    members: common::store::MemoryStore<String>,
+    builders: common::store::MemoryStore<BigInt>,
+}
+
+/// Runs one block through store_builder_members, store_builders and map_builder_stats
+fn block(stores: &Stores, fills: OrderFilledEvents) -> Vec<Builder> {
+    update_builder_members(fills.clone(), &stores.members);
+    update_builders(fills.clone(), stores.members.end_block(), &stores.builders);
+    stores.builders.end_block();
+
+    builder_stats(fills, |key| stores.builders.get(key)).builders
+}
+
+#[test]
+fn fills_without_a_builder_code_are_ignored() {
+    let stores = Stores::default();
+
+    // v1 fills carry no code and these v2 fills carry zero codes
+    assert!(block(&stores, common::fixture_fills("v1_ctf")).is_empty());
+    assert!(block(&stores, common::fixture_fills("v2_neg_risk")).is_empty());
+    assert!(stores.builders.keys().is_empty());
+}
+
+#[test]
+fn totals_accumulate_across_blocks() {
+    let stores = Stores::default();
+    let first = block(&stores, common::fixture_fills("v2_ctf"));
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0].id, BUILDER);
+    assert_eq!((first[0].trades_quantity, first[0].unique_traders, first[0].unique_markets), (1, 1, 1));
+    assert_eq!((first[0].collateral_volume.as_str(), first[0].total_fees.as_str()), ("5000000", "12600"));
+
+    // The same maker and token again, then a new maker on another token
+    block(&stores, common::fixture_fills("v2_ctf"));
+    let mut next = common::fixture_fills("v2_ctf");
+    next.events.retain(|f| builders::builder_code(f).is_some());
+    next.events[0].maker = BOB.to_string();
+    next.events[0].token_id = "7".to_string();
+    let builder = block(&stores, next).pop().unwrap();
+
+    assert_eq!(builder.trades_quantity, 3);
+    assert_eq!(builder.collateral_volume, "15000000");
+    assert_eq!(builder.total_fees, "37800");
+    assert_eq!((builder.unique_traders, builder.unique_markets), (2, 2));
+}
+
+#[test]
+fn takers_count_unless_filled_against_the_exchange() {
+    let stores = Stores::default();
+    let mut coded = common::fixture_fills("v2_ctf");
+    coded.events.retain(|f| builders::builder_code(f).is_some());
+
+    // The coded fill is a taker order filled against the exchange, which is not a trader
+    assert_eq!(block(&stores, coded.clone())[0].unique_traders, 1);
+
+    coded.events[0].taker = CAROL.to_string();
+    assert_eq!(block(&stores, coded)[0].unique_traders, 2);
+}
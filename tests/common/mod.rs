@@ -0,0 +1,534 @@
+//! Block fixtures and expected outputs under `tests/fixtures`.
+//!
+//! `{name}.json` is a block trimmed to the fields the handlers read: number, hash, timestamp and,
+//! per transaction, hash, from, to, index, gas_used, gas_price and logs (address, topics, data,
+//! index, block_index, ordinal). A raw `sf.ethereum.type.v2.Block` saved as `{name}.binpb` takes
+//! precedence when present. `{name}.expected.json` maps each handler name to its output.
+#![allow(dead_code)]
+
+pub mod store;
+
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use polymarket_orderbook_substreams::neg_risk::ArbitrageParams;
+use polymarket_orderbook_substreams::pb::polymarket::orderbook::v1::{
+    Account, Accounts, AdminEvent, ConditionPrice, EventParam, ExchangeEvents, GlobalOrderbookStats, MarketOrderbook, MarketOrderbooks,
+    NegRiskQuestion, NegRiskQuestions, OrderFilledEvent, OrderFilledEvents, OrdersMatchedEvent, OrdersMatchedEvents, OutcomePrice,
+    RegisteredToken, TokenPrice, TradingOpportunity,
+};
+use polymarket_orderbook_substreams::registry::{self, ContractRegistry};
+use polymarket_orderbook_substreams::{
+    combine_order_fills, condition_prices, extract_exchange_events, neg_risk, neg_risk_arbitrage, update_condition_token_prices,
+    update_neg_risk_prices, update_neg_risk_questions, update_registered_tokens,
+};
+use prost::Message;
+use prost_types::Timestamp;
+use serde_json::{json, Value};
+use substreams::pb::substreams::Clock;
+use substreams::scalar::BigInt;
+use substreams_ethereum::pb::eth::v2 as eth;
+
+/// topic0 of the NegRiskAdapter's `QuestionPrepared(bytes32,bytes32,uint256,bytes)`
+const QUESTION_PREPARED: &str = "aac410f87d423a922a7b226ac68f0c2eaf5bf6d15e644ac0758c7f96e2c253f7";
+
+/// Token the v2 Neg Risk fixture block trades
+pub const V2_NEG_RISK_TOKEN: &str = "52114319501245915516055106046884209969926127482827954674443846427813813222426";
+
+/// Exchange contract that matches the synthetic fills below, and the taker of `maker_fill`
+pub const EXCHANGE: &str = "4bfb41d5b3570defd03c39a9a4d8de6bd8b8982e";
+pub const TAKER: &str = "d42f6a1634a3707e27cbae14ca966068e5d1047d";
+
+fn fixture_path(file: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(file)
+}
+
+pub fn load_block(name: &str) -> eth::Block {
+    let binpb = fixture_path(&format!("{}.binpb", name));
+    if binpb.exists() {
+        let bytes = fs::read(&binpb).unwrap();
+        return eth::Block::decode(bytes.as_slice()).unwrap_or_else(|e| panic!("decoding {:?}: {}", binpb, e));
+    }
+
+    let raw = read_json(&format!("{}.json", name));
+    let number = raw["number"].as_u64().unwrap();
+
+    eth::Block {
+        number,
+        hash: bytes(&raw["hash"]),
+        header: Some(eth::BlockHeader {
+            number,
+            hash: bytes(&raw["hash"]),
+            timestamp: Some(Timestamp {
+                seconds: raw["timestamp"].as_i64().unwrap(),
+                nanos: 0,
+            }),
+            ..Default::default()
+        }),
+        transaction_traces: raw["transactions"].as_array().unwrap().iter().map(transaction).collect(),
+        ..Default::default()
+    }
+}
+
+pub fn load_expected(name: &str) -> Value {
+    read_json(&format!("{}.expected.json", name))
+}
+
+/// Clock the runtime would hand to handlers for `blk`
+pub fn clock(blk: &eth::Block) -> Clock {
+    Clock {
+        id: substreams::Hex::encode(&blk.hash),
+        number: blk.number,
+        timestamp: blk.header.as_ref().and_then(|h| h.timestamp),
+    }
+}
+
+/// Fills of fixture block `name`, through map_exchange_events and map_all_order_fills against the
+/// Polygon registry
+pub fn fixture_fills(name: &str) -> OrderFilledEvents {
+    let blk = load_block(name);
+    let events = extract_exchange_events(&ContractRegistry::default(), &blk);
+    combine_order_fills(clock(&blk), events)
+}
+
+/// Appends `log` to the receipt of `trx` and to its root call, as a fixture log would be
+pub fn push_log(trx: &mut eth::TransactionTrace, log: eth::Log) {
+    trx.receipt.get_or_insert_with(Default::default).logs.push(log.clone());
+    trx.calls[0].logs.push(log);
+}
+
+/// Runs blocks through Layer 1 to Layer 3 the way the runtime schedules them, with the
+/// foundational stores held in memory across blocks
+pub struct Replay {
+    pub registry: ContractRegistry,
+    pub markets: store::MemoryStore<MarketOrderbook>,
+    pub traders: store::MemoryStore<Account>,
+    pub global: store::MemoryStore<GlobalOrderbookStats>,
+    pub maker_levels: store::MemoryStore<BigInt>,
+    pub last_prices: store::MemoryStore<TokenPrice>,
+    pub volatility: store::MemoryStore<BigInt>,
+}
+
+/// Layer 3 outputs of one replayed block
+pub struct BlockOutputs {
+    pub fills: OrderFilledEvents,
+    pub market_orderbooks: MarketOrderbooks,
+    pub trader_accounts: Accounts,
+    pub global_stats: GlobalOrderbookStats,
+}
+
+impl Replay {
+    pub fn new() -> Self {
+        Replay {
+            registry: ContractRegistry::default(),
+            markets: store::MemoryStore::new(),
+            traders: store::MemoryStore::new(),
+            global: store::MemoryStore::new(),
+            maker_levels: store::MemoryStore::new(),
+            last_prices: store::MemoryStore::new(),
+            volatility: store::MemoryStore::new(),
+        }
+    }
+
+    pub fn process(&self, blk: &eth::Block) -> BlockOutputs {
+        use polymarket_orderbook_substreams as pkg;
+
+        let events = pkg::extract_exchange_events(&self.registry, blk);
+        let fills = pkg::combine_order_fills(clock(blk), events);
+
+        pkg::update_markets(fills.clone(), &self.markets);
+        pkg::update_traders(fills.clone(), &self.traders);
+        pkg::update_global_stats(fills.clone(), &self.global);
+        pkg::update_maker_levels(fills.clone(), &self.maker_levels);
+        self.maker_levels.end_block();
+        pkg::update_last_price(fills.clone(), &self.last_prices);
+        pkg::update_volatility_accumulators(&[3600], clock(blk), self.last_prices.end_block(), &self.volatility);
+        self.volatility.end_block();
+
+        BlockOutputs {
+            market_orderbooks: pkg::market_orderbooks(
+                fills.clone(),
+                self.markets.end_block(),
+                |key| self.maker_levels.get(key),
+                3600,
+                |key| self.volatility.get(key),
+                |_| None,
+            ),
+            trader_accounts: pkg::trader_accounts(fills.clone(), self.traders.end_block()),
+            global_stats: pkg::global_orderbook_stats(fills.clone(), self.global.end_block()),
+            fills,
+        }
+    }
+}
+
+/// A v1 Neg Risk `TokenRegistered` admin event
+pub fn token_registered(token0: &str, token1: &str, condition_id: &str, ordinal: u64) -> AdminEvent {
+    let param = |name: &str, value: &str| EventParam {
+        name: name.to_string(),
+        value: value.to_string(),
+    };
+    AdminEvent {
+        event_type: "TokenRegistered".to_string(),
+        exchange: "neg_risk".to_string(),
+        exchange_version: "v1".to_string(),
+        params: vec![param("token0", token0), param("token1", token1), param("condition_id", condition_id)],
+        block_number: 65_000_100,
+        ordinal,
+        ..Default::default()
+    }
+}
+
+/// Stores the tokens `admin_events` register, against the Polygon registry
+pub fn register_tokens(store: &store::MemoryStore<RegisteredToken>, admin_events: Vec<AdminEvent>) {
+    update_registered_tokens(
+        &ContractRegistry::default(),
+        ExchangeEvents {
+            admin_events,
+            ..Default::default()
+        },
+        NegRiskQuestions::default(),
+        store,
+    );
+}
+
+/// Token ID of outcome `index_set` (1 is YES, 2 is NO) of `condition_id` on the Neg Risk exchange,
+/// the token `token_registered` events must name to be recognized
+pub fn outcome_token(condition_id: &str, index_set: u8) -> String {
+    let collateral = hex::decode(registry::POLYGON_NEG_RISK_WRAPPED_COLLATERAL).unwrap();
+    neg_risk::position_id(&collateral, &hex::decode(condition_id).unwrap(), index_set)
+}
+
+pub fn yes(condition_id: &str) -> String {
+    outcome_token(condition_id, 1)
+}
+
+pub fn no(condition_id: &str) -> String {
+    outcome_token(condition_id, 2)
+}
+
+/// Appends the `QuestionPrepared` logs of questions `0..count` of `market` to `trx`
+pub fn prepare_questions(trx: &mut eth::TransactionTrace, market: &str, count: u8) {
+    for index in 0..count {
+        push_log(trx, question_prepared_log(market, index, 100 + index as u64));
+    }
+}
+
+/// A `QuestionPrepared` log of the Polygon NegRiskAdapter for question `index` of `market`,
+/// whose question ID is `market` with its last byte set to `index`
+pub fn question_prepared_log(market: &str, index: u8, ordinal: u64) -> eth::Log {
+    let mut question_id = hex::decode(market).unwrap();
+    question_id[31] = index;
+
+    // (uint256 index, bytes data) with empty data
+    let mut data = vec![0u8; 96];
+    data[31] = index;
+    data[63] = 0x40;
+
+    eth::Log {
+        address: hex::decode(neg_risk::POLYGON_NEG_RISK_ADAPTER).unwrap(),
+        topics: vec![hex::decode(QUESTION_PREPARED).unwrap(), hex::decode(market).unwrap(), question_id],
+        data,
+        index: ordinal as u32,
+        block_index: ordinal as u32,
+        ordinal,
+    }
+}
+
+/// Rewrites the v2 fills and matches of `blk` trading token `from` to trade `to`; v2 events carry
+/// the token ID in their second data word
+pub fn replace_token_id(blk: &mut eth::Block, from: &str, to: &str) {
+    let word = |token_id: &str| {
+        let (_, bytes) = BigInt::from_str(token_id).unwrap().to_bytes_be();
+        let mut word = vec![0u8; 32 - bytes.len()];
+        word.extend(bytes);
+        word
+    };
+    let (from, to) = (word(from), word(to));
+
+    for trx in &mut blk.transaction_traces {
+        let receipt_logs = trx.receipt.iter_mut().flat_map(|r| r.logs.iter_mut());
+        let call_logs = trx.calls.iter_mut().flat_map(|c| c.logs.iter_mut());
+        for log in receipt_logs.chain(call_logs) {
+            if log.data.len() >= 64 && log.data[32..64] == from[..] {
+                log.data[32..64].copy_from_slice(&to);
+            }
+        }
+    }
+}
+
+/// A v1 fill on `exchange` buying `tokens` of `token_id` for `collateral`
+pub fn buy_fill(exchange: &str, token_id: &str, collateral: u64, tokens: u64, ordinal: u64) -> OrderFilledEvent {
+    OrderFilledEvent {
+        token_id: token_id.to_string(),
+        maker_asset_id: "0".to_string(),
+        taker_asset_id: token_id.to_string(),
+        maker_amount_filled: collateral.to_string(),
+        taker_amount_filled: tokens.to_string(),
+        exchange: exchange.to_string(),
+        exchange_version: "v1".to_string(),
+        block_number: 65_000_200,
+        ordinal,
+        ..Default::default()
+    }
+}
+
+fn read_json(file: &str) -> Value {
+    let path = fixture_path(file);
+    let raw = fs::read_to_string(&path).unwrap_or_else(|e| panic!("reading {:?}: {}", path, e));
+    serde_json::from_str(&raw).unwrap_or_else(|e| panic!("parsing {:?}: {}", path, e))
+}
+
+/// Hex bytes, left-padded to whole bytes: quantities such as `gas_price` come as JSON-RPC
+/// quantities without leading zeros
+fn bytes(value: &Value) -> Vec<u8> {
+    let s = value.as_str().unwrap();
+    let s = s.strip_prefix("0x").unwrap_or(s);
+    if s.len() % 2 == 1 {
+        return hex::decode(format!("0{}", s)).unwrap();
+    }
+    hex::decode(s).unwrap()
+}
+
+/// A successful transaction whose logs are all emitted by its root call
+fn transaction(raw: &Value) -> eth::TransactionTrace {
+    let logs: Vec<eth::Log> = raw["logs"].as_array().unwrap().iter().map(log).collect();
+
+    eth::TransactionTrace {
+        hash: bytes(&raw["hash"]),
+        from: bytes(&raw["from"]),
+        to: bytes(&raw["to"]),
+        index: raw["index"].as_u64().unwrap() as u32,
+        gas_used: raw["gas_used"].as_u64().unwrap(),
+        gas_price: Some(eth::BigInt { bytes: bytes(&raw["gas_price"]) }),
+        status: eth::TransactionTraceStatus::Succeeded as i32,
+        receipt: Some(eth::TransactionReceipt {
+            logs: logs.clone(),
+            ..Default::default()
+        }),
+        calls: vec![eth::Call {
+            caller: bytes(&raw["from"]),
+            address: bytes(&raw["to"]),
+            logs,
+            ..Default::default()
+        }],
+        ..Default::default()
+    }
+}
+
+fn log(raw: &Value) -> eth::Log {
+    eth::Log {
+        address: bytes(&raw["address"]),
+        topics: raw["topics"].as_array().unwrap().iter().map(bytes).collect(),
+        data: bytes(&raw["data"]),
+        index: raw["index"].as_u64().unwrap() as u32,
+        block_index: raw["block_index"].as_u64().unwrap() as u32,
+        ordinal: raw["ordinal"].as_u64().unwrap(),
+    }
+}
+
+fn timestamp_json(ts: &Option<Timestamp>) -> Value {
+    match ts {
+        Some(ts) => json!({ "seconds": ts.seconds, "nanos": ts.nanos }),
+        None => Value::Null,
+    }
+}
+
+pub fn order_fills_json(fills: &OrderFilledEvents) -> Value {
+    json!({
+        "events": fills.events.iter().map(order_fill_json).collect::<Vec<_>>(),
+        "block_number": fills.block_number,
+        "block_hash": fills.block_hash,
+        "timestamp": timestamp_json(&fills.timestamp),
+    })
+}
+
+pub fn orders_matched_json(matches: &OrdersMatchedEvents) -> Value {
+    json!({
+        "events": matches.events.iter().map(order_matched_json).collect::<Vec<_>>(),
+        "block_number": matches.block_number,
+        "block_hash": matches.block_hash,
+        "timestamp": timestamp_json(&matches.timestamp),
+    })
+}
+
+fn order_fill_json(e: &OrderFilledEvent) -> Value {
+    json!({
+        "id": e.id,
+        "transaction_hash": e.transaction_hash,
+        "timestamp": timestamp_json(&e.timestamp),
+        "order_hash": e.order_hash,
+        "maker": e.maker,
+        "taker": e.taker,
+        "maker_asset_id": e.maker_asset_id,
+        "taker_asset_id": e.taker_asset_id,
+        "maker_amount_filled": e.maker_amount_filled,
+        "taker_amount_filled": e.taker_amount_filled,
+        "fee": e.fee,
+        "block_number": e.block_number,
+        "side": e.side,
+        "price": e.price,
+        "ordinal": e.ordinal,
+        "exchange_version": e.exchange_version,
+        "token_id": e.token_id,
+        "side_raw": e.side_raw,
+        "builder": e.builder,
+        "metadata": e.metadata,
+        "metadata_layout": e.metadata_layout,
+        "metadata_fields": e.metadata_fields.iter().map(|f| json!({"name": f.name, "value": f.value})).collect::<Vec<_>>(),
+        "tx_from": e.tx_from,
+        "tx_to": e.tx_to,
+        "tx_index": e.tx_index,
+        "gas_used": e.gas_used,
+        "effective_gas_price": e.effective_gas_price,
+        "call_address": e.call_address,
+        "log_index": e.log_index,
+        "exchange": e.exchange,
+    })
+}
+
+fn order_matched_json(e: &OrdersMatchedEvent) -> Value {
+    json!({
+        "id": e.id,
+        "timestamp": timestamp_json(&e.timestamp),
+        "maker_asset_id": e.maker_asset_id,
+        "taker_asset_id": e.taker_asset_id,
+        "maker_amount_filled": e.maker_amount_filled,
+        "taker_amount_filled": e.taker_amount_filled,
+        "block_number": e.block_number,
+        "ordinal": e.ordinal,
+        "exchange_version": e.exchange_version,
+        "taker_order_hash": e.taker_order_hash,
+        "taker_order_maker": e.taker_order_maker,
+        "token_id": e.token_id,
+        "side_raw": e.side_raw,
+        "tx_from": e.tx_from,
+        "tx_to": e.tx_to,
+        "tx_index": e.tx_index,
+        "gas_used": e.gas_used,
+        "effective_gas_price": e.effective_gas_price,
+        "call_address": e.call_address,
+        "exchange": e.exchange,
+        "transaction_hash": e.transaction_hash,
+    })
+}
+
+/// A fill of 1000 tokens of token "7" between a maker and `TAKER` through `EXCHANGE`, the maker
+/// bidding (paying `collateral`) or asking (paying the tokens)
+pub fn maker_fill(bidding: bool, collateral: u64, ordinal: u64) -> OrderFilledEvent {
+    let fill = OrderFilledEvent {
+        id: format!("0xhash-{}", ordinal),
+        transaction_hash: "0xhash".to_string(),
+        taker: TAKER.to_string(),
+        call_address: EXCHANGE.to_string(),
+        ..buy_fill("ctf", "7", collateral, 1000, ordinal)
+    };
+    if bidding {
+        return fill;
+    }
+    OrderFilledEvent {
+        maker_asset_id: fill.taker_asset_id.clone(),
+        taker_asset_id: fill.maker_asset_id.clone(),
+        maker_amount_filled: fill.taker_amount_filled.clone(),
+        taker_amount_filled: fill.maker_amount_filled.clone(),
+        ..fill
+    }
+}
+
+/// A `maker_fill` in which the maker `buyer` pays 500 collateral to the taker `seller`
+pub fn fill_between(buyer: &str, seller: &str, ordinal: u64) -> OrderFilledEvent {
+    OrderFilledEvent {
+        maker: buyer.to_string(),
+        taker: seller.to_string(),
+        ..maker_fill(true, 500, ordinal)
+    }
+}
+
+/// A CTF `buy_fill` of 1000 tokens of `token_id` at `seconds`
+pub fn fill_at(token_id: &str, collateral: u64, seconds: i64, ordinal: u64) -> OrderFilledEvent {
+    OrderFilledEvent {
+        timestamp: timestamp(seconds),
+        ..buy_fill("ctf", token_id, collateral, 1000, ordinal)
+    }
+}
+
+/// `fills` as the output of map_all_order_fills for a block at `seconds`
+pub fn block_fills(seconds: i64, fills: Vec<OrderFilledEvent>) -> OrderFilledEvents {
+    OrderFilledEvents {
+        events: fills,
+        timestamp: timestamp(seconds),
+        ..Default::default()
+    }
+}
+
+pub fn timestamp(seconds: i64) -> Option<Timestamp> {
+    Some(Timestamp { seconds, nanos: 0 })
+}
+
+pub fn decimal(value: &str) -> BigDecimal {
+    BigDecimal::from_str(value).unwrap()
+}
+
+/// Stores of the neg-risk and condition-price modules
+#[derive(Default)]
+pub struct Outcomes {
+    pub questions: store::MemoryStore<NegRiskQuestion>,
+    pub tokens: store::MemoryStore<RegisteredToken>,
+    pub outcome_prices: store::MemoryStore<OutcomePrice>,
+    pub token_prices: store::MemoryStore<TokenPrice>,
+}
+
+impl Outcomes {
+    /// Registers the `yes` and `no` tokens of each of `conditions` through `TokenRegistered`, both
+    /// ways round as the Neg Risk exchange does
+    pub fn register(&self, conditions: &[&str]) {
+        let mut admin_events = vec![];
+        for (i, condition) in conditions.iter().enumerate() {
+            admin_events.push(token_registered(&yes(condition), &no(condition), condition, 2 * i as u64));
+            admin_events.push(token_registered(&no(condition), &yes(condition), condition, 2 * i as u64 + 1));
+        }
+        register_tokens(&self.tokens, admin_events);
+    }
+
+    /// Runs `blk` through map_neg_risk_questions and store_neg_risk_questions
+    pub fn prepare(&self, blk: &eth::Block) -> NegRiskQuestions {
+        let questions = neg_risk::extract_neg_risk_questions(&[neg_risk::POLYGON_NEG_RISK_ADAPTER.to_string()], blk);
+        update_neg_risk_questions(questions.clone(), &self.questions);
+        questions
+    }
+
+    /// Runs one block of fills through store_neg_risk_prices and map_neg_risk_arbitrage
+    pub fn arbitrage(&self, fills: Vec<OrderFilledEvent>) -> Vec<TradingOpportunity> {
+        let fills = OrderFilledEvents {
+            events: fills,
+            block_number: 65_000_200,
+            ..Default::default()
+        };
+        update_neg_risk_prices(
+            fills.clone(),
+            |token_id| self.tokens.get(&format!("token:{}", token_id)),
+            |condition_id| self.questions.get(&format!("condition:{}", condition_id)),
+            &self.outcome_prices,
+        );
+        neg_risk_arbitrage(
+            &ArbitrageParams::default(),
+            fills,
+            |key| self.tokens.get(key),
+            |key| self.questions.get(key),
+            |key| self.outcome_prices.get(key),
+        )
+        .opportunities
+    }
+
+    /// Runs one block of fills through store_condition_token_prices and map_condition_prices
+    pub fn condition_prices(&self, fills: Vec<OrderFilledEvent>) -> Vec<ConditionPrice> {
+        let fills = OrderFilledEvents {
+            events: fills,
+            block_number: 65_000_200,
+            ..Default::default()
+        };
+        update_condition_token_prices(fills.clone(), |token_id| self.tokens.get(&format!("token:{}", token_id)), &self.token_prices);
+        condition_prices(fills, |key| self.tokens.get(key), |key| self.token_prices.get(key)).prices
+    }
+}
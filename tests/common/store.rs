@@ -0,0 +1,160 @@
+//! In-memory stand-in for the substreams store runtime.
+//!
+//! `MemoryStore` implements the set (and, for `BigInt`, add) store traits the `update_*` store
+//! bodies are generic over, keeps the current value of every key across blocks, and records the
+//! deltas of the block in progress.
+//! `end_block` hands those deltas to the Layer 3 bodies exactly as the runtime would: as
+//! `DeltaBigInt`, `DeltaString` or `DeltaProto` depending on the value type (see `StoreValue`).
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+use polymarket_orderbook_substreams::pb::polymarket::orderbook::v1::{
+    Account, GlobalOrderbookStats, MarketOrderbook, NegRiskQuestion, OutcomePrice, RegisteredToken, TokenPrice,
+};
+use substreams::pb::substreams::store_delta::Operation;
+use substreams::scalar::BigInt;
+use substreams::store::{Delta, DeltaBigInt, DeltaProto, DeltaString, Deltas, StoreAdd, StoreDelete, StoreNew, StoreSet};
+
+/// A value a `MemoryStore` can hold, with the delta type the runtime decodes its deltas into
+pub trait StoreValue: Default + Clone {
+    type Delta: Delta;
+
+    fn delta(operation: Operation, ordinal: u64, key: String, old_value: Self, new_value: Self) -> Self::Delta;
+}
+
+impl StoreValue for BigInt {
+    type Delta = DeltaBigInt;
+
+    fn delta(operation: Operation, ordinal: u64, key: String, old_value: Self, new_value: Self) -> DeltaBigInt {
+        DeltaBigInt {
+            operation,
+            ordinal,
+            key,
+            old_value,
+            new_value,
+        }
+    }
+}
+
+impl StoreValue for String {
+    type Delta = DeltaString;
+
+    fn delta(operation: Operation, ordinal: u64, key: String, old_value: Self, new_value: Self) -> DeltaString {
+        DeltaString {
+            operation,
+            ordinal,
+            key,
+            old_value,
+            new_value,
+        }
+    }
+}
+
+/// `DeltaProto` requires a prost message, so proto values are listed rather than covered by a
+/// blanket impl that would overlap the `BigInt` and `String` ones
+macro_rules! proto_store_values {
+    ($($proto:ty),*) => {
+        $(
+            impl StoreValue for $proto {
+                type Delta = DeltaProto<$proto>;
+
+                fn delta(operation: Operation, ordinal: u64, key: String, old_value: Self, new_value: Self) -> DeltaProto<$proto> {
+                    DeltaProto {
+                        operation,
+                        ordinal,
+                        key,
+                        old_value,
+                        new_value,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+proto_store_values!(Account, GlobalOrderbookStats, MarketOrderbook, NegRiskQuestion, OutcomePrice, RegisteredToken, TokenPrice);
+
+pub struct MemoryStore<V: StoreValue> {
+    values: RefCell<BTreeMap<String, V>>,
+    deltas: RefCell<Vec<V::Delta>>,
+}
+
+impl<V: StoreValue> MemoryStore<V> {
+    /// `StoreNew::new`, callable without the trait in scope
+    pub fn new() -> Self {
+        <Self as StoreNew>::new()
+    }
+
+    pub fn get(&self, key: &str) -> Option<V> {
+        self.values.borrow().get(key).cloned()
+    }
+
+    /// Keys currently set, in lexicographic order
+    pub fn keys(&self) -> Vec<String> {
+        self.values.borrow().keys().cloned().collect()
+    }
+
+    /// Closes the current block and returns its deltas in write order
+    pub fn end_block(&self) -> Deltas<V::Delta> {
+        Deltas {
+            deltas: self.deltas.borrow_mut().drain(..).collect(),
+        }
+    }
+}
+
+impl<V: StoreValue> Default for MemoryStore<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: StoreValue> StoreNew for MemoryStore<V> {
+    fn new() -> Self {
+        MemoryStore {
+            values: RefCell::new(BTreeMap::new()),
+            deltas: RefCell::new(vec![]),
+        }
+    }
+}
+
+impl<V: StoreValue> StoreDelete for MemoryStore<V> {
+    fn delete_prefix(&self, ord: i64, prefix: &String) {
+        let mut values = self.values.borrow_mut();
+        let keys: Vec<String> = values.keys().filter(|k| k.starts_with(prefix.as_str())).cloned().collect();
+
+        for key in keys {
+            let old_value = values.remove(&key).unwrap();
+            self.deltas.borrow_mut().push(V::delta(Operation::Delete, ord as u64, key, old_value, V::default()));
+        }
+    }
+}
+
+impl<V: StoreValue> StoreSet<V> for MemoryStore<V> {
+    fn set<K: AsRef<str>>(&self, ord: u64, key: K, value: &V) {
+        let key = key.as_ref().to_string();
+        let old_value = self.values.borrow_mut().insert(key.clone(), value.clone());
+
+        let operation = if old_value.is_some() { Operation::Update } else { Operation::Create };
+        self.deltas.borrow_mut().push(V::delta(operation, ord, key, old_value.unwrap_or_default(), value.clone()));
+    }
+
+    fn set_many<K: AsRef<str>>(&self, ord: u64, keys: &Vec<K>, value: &V) {
+        for key in keys {
+            self.set(ord, key, value);
+        }
+    }
+}
+
+impl StoreAdd<BigInt> for MemoryStore<BigInt> {
+    fn add<K: AsRef<str>>(&self, ord: u64, key: K, value: BigInt) {
+        let current = self.get(key.as_ref()).unwrap_or_else(BigInt::zero);
+        self.set(ord, key, &(current + value));
+    }
+
+    fn add_many<K: AsRef<str>>(&self, ord: u64, keys: &Vec<K>, value: BigInt) {
+        for key in keys {
+            self.add(ord, key, value.clone());
+        }
+    }
+}
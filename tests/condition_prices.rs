@@ -0,0 +1,86 @@
+mod common;
+
+use polymarket_orderbook_substreams::registry::ContractRegistry;
+use polymarket_orderbook_substreams::{combine_order_fills, extract_exchange_events, update_registered_tokens};
+
+const CONDITION_A: &str = "aa00000000000000000000000000000000000000000000000000000000000000";
+const CONDITION_B: &str = "bb00000000000000000000000000000000000000000000000000000000000000";
+
+/// Conditions A and B with their YES and NO tokens registered
+fn registered() -> common::Outcomes {
+    let outcomes = common::Outcomes::default();
+    outcomes.register(&[CONDITION_A, CONDITION_B]);
+    outcomes
+}
+
+#[test]
+fn both_sides_traded() {
+    let stores = registered();
+    let prices = stores.condition_prices(vec![
+        common::buy_fill("ctf", &common::yes(CONDITION_A), 620, 1000, 1),
+        common::buy_fill("ctf", &common::no(CONDITION_A), 400, 1000, 2),
+    ]);
+
+    assert_eq!(prices.len(), 1);
+    let a = &prices[0];
+    assert_eq!((a.yes_token_id.clone(), a.no_token_id.clone()), (common::yes(CONDITION_A), common::no(CONDITION_A)));
+    assert_eq!((a.yes_price.as_str(), a.no_price.as_str()), ("0.62", "0.4"));
+    assert_eq!(a.implied_yes_probability, "0.61");
+    assert_eq!(a.price_sum, "1.02");
+    assert_eq!(a.deviation, "0.02");
+}
+
+#[test]
+fn one_side_traded() {
+    let stores = registered();
+    let prices = stores.condition_prices(vec![common::buy_fill("neg_risk", &common::no(CONDITION_B), 250, 1000, 1)]);
+
+    assert_eq!(prices.len(), 1);
+    let b = &prices[0];
+    assert_eq!(b.condition_id, CONDITION_B);
+    assert_eq!((b.yes_token_id.clone(), b.no_token_id.clone()), (common::yes(CONDITION_B), common::no(CONDITION_B)));
+    assert_eq!(b.yes_price, "");
+    assert_eq!(b.implied_yes_probability, "0.75");
+    assert_eq!((b.price_sum.as_str(), b.deviation.as_str()), ("", ""));
+}
+
+#[test]
+fn prices_carry_over_between_blocks() {
+    let stores = registered();
+    stores.condition_prices(vec![common::buy_fill("ctf", &common::yes(CONDITION_A), 500, 1000, 1)]);
+    let prices = stores.condition_prices(vec![common::buy_fill("ctf", &common::no(CONDITION_A), 450, 1000, 1)]);
+
+    let a = &prices[0];
+    assert_eq!((a.yes_price.as_str(), a.no_price.as_str()), ("0.5", "0.45"));
+    assert_eq!(a.deviation, "-0.05");
+}
+
+#[test]
+fn unregistered_tokens_are_ignored() {
+    let stores = registered();
+    assert!(stores.condition_prices(vec![common::buy_fill("ctf", "99", 500, 1000, 1)]).is_empty());
+}
+
+#[test]
+fn v2_market_is_priced_from_question_tokens() {
+    let registry = ContractRegistry::default();
+    let stores = common::Outcomes::default();
+
+    // A v2-only neg-risk question whose NO token the fixture fills trade
+    let market = "cdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd00";
+    let mut blk = common::load_block("v2_neg_risk");
+    common::prepare_questions(&mut blk.transaction_traces[0], market, 1);
+    let questions = stores.prepare(&blk);
+    let condition = questions.questions[0].condition_id.clone();
+    common::replace_token_id(&mut blk, common::V2_NEG_RISK_TOKEN, &common::no(&condition));
+
+    let events = extract_exchange_events(&registry, &blk);
+    update_registered_tokens(&registry, events.clone(), questions, &stores.tokens);
+    let prices = stores.condition_prices(combine_order_fills(common::clock(&blk), events).events);
+
+    assert_eq!(prices.len(), 1);
+    let price = &prices[0];
+    assert_eq!(price.condition_id, condition);
+    assert_eq!((price.yes_token_id.clone(), price.no_token_id.clone()), (common::yes(&condition), common::no(&condition)));
+    assert_eq!((price.no_price.as_str(), price.implied_yes_probability.as_str()), ("0.25", "0.75"));
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use polymarket_orderbook_substreams::registry::ContractRegistry;
+use polymarket_orderbook_substreams::{consistency_checks, extract_exchange_events};
+use substreams::scalar::BigInt;
+
+const BLOCKS: [&str; 5] = ["v1_ctf", "v1_neg_risk", "cutover", "v2_ctf", "v2_neg_risk"];
+
+fn checks(name: &str) -> Vec<String> {
+    let blk = common::load_block(name);
+    let events = extract_exchange_events(&ContractRegistry::default(), &blk);
+    consistency_checks(common::clock(&blk), events, |_| None)
+        .violations
+        .into_iter()
+        .map(|v| v.check)
+        .collect()
+}
+
+#[test]
+fn fixture_blocks_are_consistent() {
+    for name in BLOCKS {
+        assert_eq!(checks(name), Vec::<String>::new(), "violations in {}", name);
+    }
+}
+
+#[test]
+fn fee_above_max_rate() {
+    let blk = common::load_block("v2_ctf");
+    let events = extract_exchange_events(&ContractRegistry::default(), &blk);
+
+    // 10 bps of the 8,000,000 token leg allows a fee of 8,000; the taker fill charged 12,600
+    let output = consistency_checks(common::clock(&blk), events, |key| {
+        (key == "max_fee_rate:ctf").then(|| BigInt::from(10))
+    });
+
+    assert_eq!(output.violations.len(), 1);
+    let violation = &output.violations[0];
+    assert_eq!(violation.check, "fee_above_max_rate");
+    assert_eq!(violation.exchange_version, "v2");
+    assert_eq!(violation.actual, "12600");
+    assert_eq!(output.block_number, blk.number);
+}
+
+#[test]
+fn match_amounts_disagree_with_fills() {
+    let blk = common::load_block("v2_ctf");
+    let mut events = extract_exchange_events(&ContractRegistry::default(), &blk);
+    let matched = &mut events.orders_matched[0];
+    matched.maker_amount_filled = "1".to_string();
+    let expected = format!("1/{}", matched.taker_amount_filled);
+
+    // A maker fill that under-reports its token leg
+    let taker_fill = events.order_fills.iter().find(|f| f.taker == f.call_address).unwrap().clone();
+    let maker_fill = events
+        .order_fills
+        .iter_mut()
+        .find(|f| f.ordinal < taker_fill.ordinal && f.taker == taker_fill.maker)
+        .unwrap();
+    if maker_fill.maker_asset_id == "0" {
+        maker_fill.taker_amount_filled = "0".to_string();
+    } else {
+        maker_fill.maker_amount_filled = "0".to_string();
+    }
+
+    let output = consistency_checks(common::clock(&blk), events, |_| None);
+    let found: Vec<&str> = output.violations.iter().map(|v| v.check.as_str()).collect();
+    assert!(found.contains(&"taker_fill_vs_match"), "{:?}", found);
+    assert!(found.contains(&"maker_fill_sum"), "{:?}", found);
+
+    let vs_match = output.violations.iter().find(|v| v.check == "taker_fill_vs_match").unwrap();
+    assert_eq!(vs_match.expected, expected);
+    assert_eq!(vs_match.order_hash, taker_fill.order_hash);
+}
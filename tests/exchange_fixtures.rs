@@ -0,0 +1,235 @@
+mod common;
+
+use polymarket_orderbook_substreams::registry::{self, ContractRegistry};
+use substreams_ethereum::pb::eth::v2 as eth;
+use polymarket_orderbook_substreams::{
+    combine_order_fills, exchange_index_keys, extract_exchange_events, order_fills_view, orders_matched_view,
+};
+
+const ORDER_FILLED_VIEWS: [(&str, &str, &str); 4] = [
+    ("map_ctf_exchange_order_filled", "ctf", "v1"),
+    ("map_neg_risk_exchange_order_filled", "neg_risk", "v1"),
+    ("map_ctf_exchange_v2_order_filled", "ctf", "v2"),
+    ("map_neg_risk_exchange_v2_order_filled", "neg_risk", "v2"),
+];
+
+const ORDERS_MATCHED_VIEWS: [(&str, &str, &str); 4] = [
+    ("map_ctf_exchange_orders_matched", "ctf", "v1"),
+    ("map_neg_risk_exchange_orders_matched", "neg_risk", "v1"),
+    ("map_ctf_exchange_v2_orders_matched", "ctf", "v2"),
+    ("map_neg_risk_exchange_v2_orders_matched", "neg_risk", "v2"),
+];
+
+/// Runs the Layer 1 handlers over a fixture block and compares every output with the expected file
+fn assert_fixture(name: &str) {
+    let blk = common::load_block(name);
+    let expected = common::load_expected(name);
+    let events = extract_exchange_events(&ContractRegistry::default(), &blk);
+    assert!(events.undecoded_logs.is_empty(), "undecoded logs in {}", name);
+
+    for (handler, kind, version) in ORDER_FILLED_VIEWS {
+        let output = order_fills_view(events.clone(), kind, version);
+        assert_eq!(common::order_fills_json(&output), expected[handler], "{} output for {}", handler, name);
+    }
+    for (handler, kind, version) in ORDERS_MATCHED_VIEWS {
+        let output = orders_matched_view(events.clone(), kind, version);
+        assert_eq!(common::orders_matched_json(&output), expected[handler], "{} output for {}", handler, name);
+    }
+
+    let output = combine_order_fills(common::clock(&blk), events);
+    assert_eq!(
+        common::order_fills_json(&output),
+        expected["map_all_order_fills"],
+        "map_all_order_fills output for {}",
+        name
+    );
+}
+
+#[test]
+fn v1_ctf_exchange_block() {
+    assert_fixture("v1_ctf");
+}
+
+#[test]
+fn v1_neg_risk_exchange_block() {
+    assert_fixture("v1_neg_risk");
+}
+
+#[test]
+fn v2_ctf_exchange_block() {
+    assert_fixture("v2_ctf");
+}
+
+#[test]
+fn v2_neg_risk_exchange_block() {
+    assert_fixture("v2_neg_risk");
+}
+
+#[test]
+fn cutover_block() {
+    assert_fixture("cutover");
+}
+
+#[test]
+fn v2_fee_charged_is_an_admin_event() {
+    let blk = common::load_block("v2_ctf");
+    let events = extract_exchange_events(&ContractRegistry::default(), &blk);
+
+    assert_eq!(events.admin_events.len(), 1);
+    let fee = &events.admin_events[0];
+    assert_eq!(fee.event_type, "FeeCharged");
+    assert_eq!((fee.exchange.as_str(), fee.exchange_version.as_str()), ("ctf", "v2"));
+    assert_eq!(fee.params[0].value, "8a3f8b3e63d2b5c7a9e6a4e5d3f07d6e1fa0c2b9");
+    assert_eq!(fee.params[1].value, "12600");
+}
+
+#[test]
+fn v1_order_cancelled_is_an_admin_event() {
+    let mut blk = common::load_block("v1_ctf");
+    let trx = &mut blk.transaction_traces[0];
+    let exchange = trx.calls[0].logs[0].address.clone();
+
+    common::push_log(trx, eth::Log {
+        address: exchange,
+        topics: vec![
+            hex::decode("5152abf959f6564662358c2e52b702259b78bac5ee7842a0f01937e670efcc7d").unwrap(),
+            vec![0x11; 32],
+        ],
+        data: vec![],
+        index: 90,
+        block_index: 90,
+        ordinal: 90,
+    });
+
+    let events = extract_exchange_events(&ContractRegistry::default(), &blk);
+    assert!(events.undecoded_logs.is_empty());
+    let cancelled = events.admin_events.iter().find(|e| e.event_type == "OrderCancelled").unwrap();
+    assert_eq!((cancelled.exchange.as_str(), cancelled.exchange_version.as_str()), ("ctf", "v1"));
+    assert_eq!(cancelled.params[0].value, "11".repeat(32));
+}
+
+#[test]
+fn cutover_block_index_keys() {
+    let blk = common::load_block("cutover");
+    let keys = exchange_index_keys(&ContractRegistry::default(), &blk);
+
+    assert_eq!(
+        keys.keys,
+        vec![
+            "exchange:any",
+            "exchange:ctf_v1",
+            "evt_addr:0x4bfb41d5b3570defd03c39a9a4d8de6bd8b8982e",
+            "evt_sig:0xd0a08e8c493f9c94f29311604c9de1b4e8c8d4c06bd0c789af57f2d65bfec0f6",
+            "evt_sig:0xaa00869ecc7a2675aeae91fe142fd1e0c7a67e5ada2b1618f934701dd2e2d6ab",
+            "exchange:neg_risk_v2",
+            "evt_addr:0xe2222d279d744050d28e00520010520000310f59",
+            "evt_sig:0xd543adfd945773f1a62f74f0ee55a5e3b9b1a28262980ba90b1a89f2ea84d8ee",
+            "evt_sig:0x174b3811690657c217184f89418266767c87e4805d09680c39fc9c031c0cab7c",
+        ]
+    );
+}
+
+#[test]
+fn logs_before_start_block_are_ignored() {
+    let mut blk = common::load_block("v2_ctf");
+    blk.number = 84_902_352;
+
+    let events = extract_exchange_events(&ContractRegistry::default(), &blk);
+    assert!(events.order_fills.is_empty());
+    assert!(events.orders_matched.is_empty());
+    assert!(events.admin_events.is_empty());
+}
+
+#[test]
+fn reverted_logs_are_ignored() {
+    let mut blk = common::load_block("v2_ctf");
+    let registry = ContractRegistry::default();
+    let fills = extract_exchange_events(&registry, &blk).order_fills.len();
+    assert!(fills > 0);
+
+    // A reverted call keeps its logs in the trace but not in the receipt
+    let trx = &mut blk.transaction_traces[0];
+    let mut reverted = trx.calls[0].clone();
+    reverted.state_reverted = true;
+    reverted.logs.iter_mut().for_each(|log| log.block_index += 100);
+    trx.calls.push(reverted);
+
+    let events = extract_exchange_events(&registry, &blk);
+    assert_eq!(events.order_fills.len(), fills);
+    assert!(events.undecoded_logs.is_empty());
+
+    for trx in &mut blk.transaction_traces {
+        trx.status = eth::TransactionTraceStatus::Reverted as i32;
+        trx.calls.iter_mut().for_each(|call| call.state_reverted = true);
+    }
+
+    let events = extract_exchange_events(&registry, &blk);
+    assert!(events.order_fills.is_empty());
+    assert!(events.orders_matched.is_empty());
+    assert!(events.admin_events.is_empty());
+    assert!(exchange_index_keys(&registry, &blk).keys.is_empty());
+}
+
+#[test]
+fn undecodable_exchange_logs_are_quarantined() {
+    let mut blk = common::load_block("v2_ctf");
+    let trx = &mut blk.transaction_traces[0];
+    let exchange = trx.calls[0].logs[0].address.clone();
+    let order_filled_topic = trx.calls[0].logs[0].topics[0].clone();
+
+    common::push_log(trx, eth::Log {
+        address: exchange.clone(),
+        topics: vec![vec![0xab; 32]],
+        data: vec![],
+        index: 90,
+        block_index: 90,
+        ordinal: 90,
+    });
+    // An OrderFilled whose data was cut short
+    common::push_log(trx, eth::Log {
+        address: exchange,
+        topics: vec![order_filled_topic.clone(), vec![0; 32], vec![0; 32], vec![0; 32]],
+        data: vec![0; 31],
+        index: 91,
+        block_index: 91,
+        ordinal: 91,
+    });
+
+    let events = extract_exchange_events(&ContractRegistry::default(), &blk);
+    let reasons: Vec<(&str, &str)> = events
+        .undecoded_logs
+        .iter()
+        .map(|l| (l.reason.as_str(), l.event_name.as_str()))
+        .collect();
+    assert_eq!(reasons, vec![("unknown_topic", ""), ("decode_failed", "OrderFilled")]);
+
+    let failed = &events.undecoded_logs[1];
+    assert_eq!((failed.exchange.as_str(), failed.exchange_version.as_str()), ("ctf", "v2"));
+    assert_eq!(failed.topics[0], hex::encode(&order_filled_topic));
+    assert_eq!(failed.id, format!("{}-91", hex::encode(&blk.transaction_traces[0].hash)));
+}
+
+#[test]
+fn unsupported_exchange_generation_is_rejected() {
+    let collateral = registry::POLYGON_USDC;
+    let err = ContractRegistry::from_params(&format!("ctf:v3:0xe111180000d2663c0091e4f400237545b87b996b:0x{}", collateral))
+        .unwrap_err();
+    assert!(err.to_string().contains("invalid exchange version"), "{}", err);
+    assert!(ContractRegistry::from_params(&format!("amm:v2:0xe111180000d2663c0091e4f400237545b87b996b:0x{}", collateral)).is_err());
+}
+
+#[test]
+fn registry_entries_name_their_collateral() {
+    let registry = ContractRegistry::from_params(
+        "polygon neg_risk:v2:0x1111111111111111111111111111111111111111:0x2222222222222222222222222222222222222222:90000000",
+    )
+    .unwrap();
+
+    assert_eq!(registry.exchanges.len(), 5);
+    assert_eq!(registry.collateral("ctf", "v2"), Some(registry::POLYGON_USDC));
+    let added = &registry.exchanges[4];
+    assert_eq!((added.collateral.as_str(), added.start_block), ("2222222222222222222222222222222222222222", 90_000_000));
+
+    // An entry without its collateral is rejected rather than read as a start block
+    assert!(ContractRegistry::from_params("ctf:v2:0x1111111111111111111111111111111111111111:90000000").is_err());
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use std::cell::Cell;
+
+use polymarket_orderbook_substreams::ladder;
+use polymarket_orderbook_substreams::pb::polymarket::orderbook::v1::{MarketOrderbook, OrderFilledEvent};
+use polymarket_orderbook_substreams::{market_orderbooks, update_maker_levels, update_markets};
+use substreams::scalar::BigInt;
+
+const T0: i64 = 1_700_002_800;
+
+#[derive(Default)]
+struct Stores {
+    markets: common::store::MemoryStore<MarketOrderbook>,
+    maker_levels: common::store::MemoryStore<BigInt>,
+}
+
+/// Runs one block through store_markets, store_maker_levels and map_market_orderbooks and
+/// returns the last orderbook emitted
+fn block(stores: &Stores, seconds: i64, fills: Vec<OrderFilledEvent>) -> MarketOrderbook {
+    let fills = common::block_fills(seconds, fills);
+    update_markets(fills.clone(), &stores.markets);
+    update_maker_levels(fills.clone(), &stores.maker_levels);
+    stores.maker_levels.end_block();
+
+    market_orderbooks(fills, stores.markets.end_block(), |key| stores.maker_levels.get(key), 3600, |_| None, |_| None)
+        .orderbooks
+        .pop()
+        .unwrap()
+}
+
+#[test]
+fn maker_fills_build_both_sides() {
+    let stores = Stores::default();
+    block(&stores, T0, vec![common::maker_fill(true, 400, 1), common::maker_fill(true, 380, 2)]);
+    let orderbook = block(&stores, T0 + 60, vec![common::maker_fill(true, 401, 1), common::maker_fill(false, 450, 2)]);
+
+    let bids: Vec<(&str, &str, u64)> =
+        orderbook.bid_levels.iter().map(|l| (l.price.as_str(), l.size.as_str(), l.order_count)).collect();
+    assert_eq!(bids, vec![("0.4", "2000", 2), ("0.38", "1000", 1)]);
+    assert_eq!(orderbook.ask_levels.len(), 1);
+
+    assert_eq!((orderbook.best_bid.as_str(), orderbook.best_ask.as_str()), ("0.4", "0.45"));
+    assert_eq!(common::decimal(&orderbook.spread), common::decimal("0.05"));
+    // 0.40 × 2000 + 0.38 × 1000 + 0.45 × 1000
+    assert_eq!(common::decimal(&orderbook.market_depth), common::decimal("1630"));
+}
+
+#[test]
+fn matched_taker_orders_are_not_levels() {
+    let stores = Stores::default();
+    // A taker order buying from an ask: the maker's fill, then the taker order's own fill
+    // paying collateral to the exchange
+    let taker_fill = OrderFilledEvent {
+        maker: common::TAKER.to_string(),
+        taker: common::EXCHANGE.to_string(),
+        ..common::maker_fill(true, 450, 2)
+    };
+    let orderbook = block(&stores, T0, vec![common::maker_fill(false, 450, 1), taker_fill]);
+
+    assert!(orderbook.bid_levels.is_empty());
+    let asks: Vec<(&str, &str, u64)> =
+        orderbook.ask_levels.iter().map(|l| (l.price.as_str(), l.size.as_str(), l.order_count)).collect();
+    assert_eq!(asks, vec![("0.45", "1000", 1)]);
+}
+
+#[test]
+fn one_sided_or_crossed_ladders_have_no_spread() {
+    let stores = Stores::default();
+    let orderbook = block(&stores, T0, vec![common::maker_fill(true, 400, 1)]);
+    assert_eq!((orderbook.best_bid.as_str(), orderbook.best_ask.as_str()), ("0.4", ""));
+    assert_eq!(common::decimal(&orderbook.spread), common::decimal("0"));
+
+    let orderbook = block(&stores, T0 + 60, vec![common::maker_fill(false, 390, 1)]);
+    assert_eq!(common::decimal(&orderbook.spread), common::decimal("0"));
+}
+
+#[test]
+fn levels_older_than_the_previous_hour_expire() {
+    let stores = Stores::default();
+    block(&stores, T0, vec![common::maker_fill(true, 400, 1)]);
+
+    // The previous hour still counts
+    let orderbook = block(&stores, T0 + 3600, vec![common::maker_fill(false, 450, 1)]);
+    assert_eq!(orderbook.best_bid, "0.4");
+
+    let orderbook = block(&stores, T0 + 7200, vec![common::maker_fill(false, 460, 1)]);
+    assert!(orderbook.bid_levels.is_empty());
+    assert_eq!(orderbook.ask_levels.len(), 2);
+    let first_window = ladder::window_prefix(ladder::window_of(T0 as u64));
+    assert!(stores.maker_levels.keys().iter().all(|k| !k.starts_with(&first_window)));
+}
+
+#[test]
+fn each_market_is_emitted_once_per_block() {
+    let stores = Stores::default();
+    let fills = common::block_fills(T0, vec![common::maker_fill(true, 400, 1), common::maker_fill(true, 380, 2), common::maker_fill(false, 450, 3)]);
+    update_markets(fills.clone(), &stores.markets);
+    update_maker_levels(fills.clone(), &stores.maker_levels);
+    stores.maker_levels.end_block();
+
+    let reads = Cell::new(0);
+    let orderbooks = market_orderbooks(
+        fills,
+        stores.markets.end_block(),
+        |key| {
+            reads.set(reads.get() + 1);
+            stores.maker_levels.get(key)
+        },
+        3600,
+        |_| None,
+        |_| None,
+    )
+    .orderbooks;
+
+    assert_eq!(orderbooks.len(), 1);
+    // The last fill's market value: the ask, whose taker paid 450 collateral
+    assert_eq!(orderbooks[0].collateral_volume, "450");
+    assert_eq!(orderbooks[0].bid_levels.len(), 2);
+
+    // The ladder was built once
+    let ladder_reads = Cell::new(0);
+    ladder::ladder("7", T0 as u64, |key| {
+        ladder_reads.set(ladder_reads.get() + 1);
+        stores.maker_levels.get(key)
+    });
+    assert_eq!(reads.get(), ladder_reads.get());
+}
+
+#[test]
+fn prices_round_to_the_nearest_tick() {
+    assert_eq!(ladder::tick_of(&common::decimal("0.4049")), 40);
+    assert_eq!(ladder::tick_of(&common::decimal("0.406")), 41);
+    assert_eq!(ladder::tick_of(&common::decimal("0.001")), 1);
+    assert_eq!(ladder::tick_of(&common::decimal("0.999")), 99);
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use polymarket_orderbook_substreams::pb::polymarket::orderbook::v1::market_alert::AlertType;
+use polymarket_orderbook_substreams::pb::polymarket::orderbook::v1::OrderFilledEvents;
+use polymarket_orderbook_substreams::registry::ContractRegistry;
+use polymarket_orderbook_substreams::alerts::AlertThresholds;
+use polymarket_orderbook_substreams::{combine_order_fills, extract_exchange_events, market_alerts, update_market_activity};
+use substreams::scalar::BigInt;
+
+/// Fills of the v1 CTF fixture block as if it were mined `hours` later, amounts multiplied by `scale`
+fn fills(hours: i64, scale: u64) -> OrderFilledEvents {
+    let blk = common::load_block("v1_ctf");
+    let mut clock = common::clock(&blk);
+    clock.timestamp.as_mut().unwrap().seconds += hours * 3600;
+
+    let events = extract_exchange_events(&ContractRegistry::default(), &blk);
+    let mut fills = combine_order_fills(clock, events);
+    for fill in &mut fills.events {
+        for amount in [&mut fill.maker_amount_filled, &mut fill.taker_amount_filled] {
+            *amount = (amount.parse::<u64>().unwrap() * scale).to_string();
+        }
+    }
+    fills
+}
+
+fn alert_types(thresholds: &str, baseline: &common::store::MemoryStore<BigInt>, block: OrderFilledEvents) -> Vec<(AlertType, String)> {
+    let thresholds = AlertThresholds::parse(thresholds).unwrap();
+    market_alerts(&thresholds, block, |key| baseline.get(key))
+        .alerts
+        .into_iter()
+        .map(|a| (AlertType::try_from(a.r#type).unwrap(), a.severity))
+        .collect()
+}
+
+#[test]
+fn steady_activity_raises_no_alerts() {
+    let store = common::store::MemoryStore::new();
+    update_market_activity(fills(0, 1), &store);
+
+    assert!(alert_types("min_baseline_fills:1", &store, fills(1, 1)).is_empty());
+}
+
+#[test]
+fn volume_spike_against_previous_hour() {
+    let store = common::store::MemoryStore::new();
+    update_market_activity(fills(0, 1), &store);
+
+    let alerts = alert_types("min_baseline_fills:1", &store, fills(1, 10));
+    assert!(alerts.contains(&(AlertType::VolumeSpike, "medium".to_string())), "{:?}", alerts);
+    // Both legs scaled alike, so the price holds and so does the set of makers
+    assert!(!alerts.iter().any(|(t, _)| matches!(t, AlertType::PriceMovement | AlertType::LiquidityChange)));
+}
+
+#[test]
+fn thin_or_stale_baselines_raise_no_alerts() {
+    let store = common::store::MemoryStore::new();
+    update_market_activity(fills(0, 1), &store);
+
+    // Too few baseline fills
+    assert!(alert_types("", &store, fills(1, 10)).is_empty());
+    // Two hours later the previous window is empty
+    assert!(alert_types("min_baseline_fills:1", &store, fills(2, 10)).is_empty());
+}
+
+#[test]
+fn windows_older_than_the_baseline_are_pruned() {
+    let store = common::store::MemoryStore::new();
+    update_market_activity(fills(0, 1), &store);
+    let first_window = store.keys()[0].split(':').next().unwrap().to_string();
+
+    update_market_activity(fills(2, 1), &store);
+    assert!(store.keys().iter().all(|k| !k.starts_with(&format!("{}:", first_window))));
+}
+
+#[test]
+fn invalid_thresholds_are_rejected() {
+    assert!(AlertThresholds::parse("volume_multiplier:0").is_err());
+    assert!(AlertThresholds::parse("spread:2").is_err());
+    assert!(AlertThresholds::parse("price_change").is_err());
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use polymarket_orderbook_substreams::metadata::{MetadataDecoder, MetadataLayout};
+use polymarket_orderbook_substreams::pb::polymarket::orderbook::v1::{EventParam, OrderFilledEvent, OrderFilledEvents};
+use polymarket_orderbook_substreams::{metadata_layouts, update_metadata_layouts};
+
+fn word(bytes: &[u8], right_aligned: bool) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    if right_aligned {
+        word[32 - bytes.len()..].copy_from_slice(bytes);
+    } else {
+        word[..bytes.len()].copy_from_slice(bytes);
+    }
+    word
+}
+
+fn decode(metadata: &[u8]) -> (String, Vec<(String, String)>) {
+    let (layout, fields) = MetadataDecoder::default().decode(metadata);
+    (layout, fields.into_iter().map(|f| (f.name, f.value)).collect())
+}
+
+fn field(name: &str, value: &str) -> Vec<(String, String)> {
+    vec![(name.to_string(), value.to_string())]
+}
+
+#[test]
+fn built_in_layouts() {
+    assert_eq!(decode(&[0u8; 32]), ("empty".to_string(), vec![]));
+    assert_eq!(decode(&word(b"polymarket-web", false)), ("utf8_tag".to_string(), field("tag", "polymarket-web")));
+    assert_eq!(decode(&word(&[0x01, 0x00], true)), ("app_id".to_string(), field("app_id", "256")));
+
+    let address = hex::decode("9d84ce0306f8551e02efef1680475fc0f1dc1344").unwrap();
+    assert_eq!(
+        decode(&word(&address, true)),
+        ("address".to_string(), field("address", "9d84ce0306f8551e02efef1680475fc0f1dc1344"))
+    );
+}
+
+#[test]
+fn unrecognized_metadata_is_unknown() {
+    // A hash-like value, a tag with bytes after its padding and a control character
+    let mut tag_then_value = word(b"tag", false);
+    tag_then_value[31] = 1;
+    for metadata in [[0xabu8; 32], tag_then_value, word(b"\x07bell", false)] {
+        assert_eq!(decode(&metadata), ("unknown".to_string(), vec![]));
+    }
+    assert_eq!(decode(&[1u8; 4]).0, "unknown");
+}
+
+/// A layout an integrator might use: a 4-byte client prefix followed by an order counter
+struct ClientOrder;
+
+impl MetadataLayout for ClientOrder {
+    fn name(&self) -> &'static str {
+        "client_order"
+    }
+
+    fn decode(&self, metadata: &[u8; 32]) -> Option<Vec<EventParam>> {
+        (metadata[..4] == *b"ACME").then(|| {
+            vec![EventParam {
+                name: "order".to_string(),
+                value: metadata[31].to_string(),
+            }]
+        })
+    }
+}
+
+#[test]
+fn added_layouts_take_precedence() {
+    let mut metadata = word(b"ACME", false);
+    let decoder = MetadataDecoder::default().with_layout(ClientOrder);
+    assert_eq!(decoder.decode(&metadata).0, "client_order");
+    assert_eq!(MetadataDecoder::default().decode(&metadata).0, "utf8_tag");
+
+    metadata[31] = 9;
+    let mut fill = OrderFilledEvent {
+        metadata: hex::encode(metadata),
+        ..Default::default()
+    };
+    decoder.apply(&mut fill);
+    assert_eq!((fill.metadata_layout.as_str(), fill.metadata_fields[0].value.as_str()), ("client_order", "9"));
+}
+
+#[test]
+fn unknown_metadata_is_counted_per_builder() {
+    let store = common::store::MemoryStore::new();
+    let fill = |builder: &str, metadata: [u8; 32]| {
+        let mut fill = OrderFilledEvent {
+            builder: builder.to_string(),
+            metadata: hex::encode(metadata),
+            ..Default::default()
+        };
+        MetadataDecoder::default().apply(&mut fill);
+        fill
+    };
+    let block = OrderFilledEvents {
+        events: vec![fill("aa", [0xab; 32]), fill("aa", [0xcd; 32]), fill("bb", [0u8; 32]), OrderFilledEvent::default()],
+        ..Default::default()
+    };
+
+    update_metadata_layouts(block.clone(), &store);
+    update_metadata_layouts(block.clone(), &store);
+    let output = metadata_layouts(block, |key| store.get(key));
+
+    let layouts: Vec<(&str, u64, u64)> =
+        output.layouts.iter().map(|l| (l.layout.as_str(), l.block_count, l.total_count)).collect();
+    assert_eq!(layouts, vec![("empty", 1, 2), ("unknown", 2, 4)]);
+    assert_eq!(output.unknown.len(), 1);
+    let unknown = &output.unknown[0];
+    assert_eq!((unknown.builder.as_str(), unknown.block_count, unknown.total_count), ("aa", 2, 4));
+    assert_eq!(unknown.sample, hex::encode([0xcd; 32]));
+}
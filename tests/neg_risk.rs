@@ -0,0 +1,145 @@
+mod common;
+
+use polymarket_orderbook_substreams::neg_risk;
+use polymarket_orderbook_substreams::registry::{self, ContractRegistry};
+use polymarket_orderbook_substreams::{combine_order_fills, extract_exchange_events, update_registered_tokens};
+
+const MARKET: &str = "ababababababababababababababababababababababababababababababab00";
+
+/// Condition IDs of the three questions of MARKET prepared by the Polygon adapter
+const CONDITIONS: [&str; 3] = [
+    "56095518ce39e46955615e682631f7f82aa1e82548aae774f98b32287e61e776",
+    "83b1d6b7305f70b2a543f44c50b7b81040a37488471e16e258be2111bc14c537",
+    "90c63fb5610cb3b62d63b774357bc34179d2d1caf3ac6942691d14ed63f91b9c",
+];
+
+/// A three-outcome market with its questions prepared and the outcome tokens of CONDITIONS registered
+fn registered_market() -> common::Outcomes {
+    let outcomes = common::Outcomes::default();
+    let mut blk = common::load_block("v1_neg_risk");
+    common::prepare_questions(&mut blk.transaction_traces[0], MARKET, 3);
+    outcomes.prepare(&blk);
+    outcomes.register(&CONDITIONS);
+    outcomes
+}
+
+#[test]
+fn question_prepared_yields_condition_ids() {
+    let stores = registered_market();
+
+    for (index, condition) in CONDITIONS.iter().enumerate() {
+        let question = stores.questions.get(&format!("condition:{}", condition)).unwrap();
+        assert_eq!((question.market_id.as_str(), question.index), (MARKET, index as u64));
+    }
+    assert_eq!(stores.questions.get(&format!("market:{}", MARKET)).unwrap().index, 2);
+}
+
+#[test]
+fn registrations_are_labelled_by_position_id() {
+    let stores = registered_market();
+
+    assert_eq!(stores.tokens.get(&format!("token:{}", common::yes(CONDITIONS[1]))).unwrap().outcome, "yes");
+    let registered = stores.tokens.get(&format!("token:{}", common::no(CONDITIONS[1]))).unwrap();
+    assert_eq!((registered.outcome.as_str(), registered.complement_token_id.clone()), ("no", common::yes(CONDITIONS[1])));
+}
+
+#[test]
+fn registration_order_does_not_decide_the_outcome() {
+    let stores = common::Outcomes::default();
+    common::register_tokens(
+        &stores.tokens,
+        vec![
+            common::token_registered(&common::no(CONDITIONS[0]), &common::yes(CONDITIONS[0]), CONDITIONS[0], 1),
+            common::token_registered(&common::yes(CONDITIONS[0]), &common::no(CONDITIONS[0]), CONDITIONS[0], 2),
+        ],
+    );
+
+    assert_eq!(stores.tokens.get(&format!("token:{}", common::no(CONDITIONS[0]))).unwrap().outcome, "no");
+    assert_eq!(stores.tokens.get(&format!("token:{}", common::yes(CONDITIONS[0]))).unwrap().outcome, "yes");
+}
+
+#[test]
+fn tokens_that_are_not_positions_of_the_condition_are_skipped() {
+    let stores = common::Outcomes::default();
+    common::register_tokens(&stores.tokens, vec![common::token_registered("11", "12", CONDITIONS[0], 1)]);
+
+    assert!(stores.tokens.keys().is_empty());
+}
+
+#[test]
+fn underpriced_yes_set_is_an_opportunity() {
+    let stores = registered_market();
+
+    // YES prices 0.30 + (1 - 0.55) + 0.15 = 0.90, the second outcome priced through its NO token
+    let opportunities = stores.arbitrage(vec![
+        common::buy_fill("neg_risk", &common::yes(CONDITIONS[0]), 300, 1000, 1),
+        common::buy_fill("neg_risk", &common::no(CONDITIONS[1]), 550, 1000, 2),
+        common::buy_fill("neg_risk", &common::yes(CONDITIONS[2]), 150, 1000, 3),
+    ]);
+
+    assert_eq!(opportunities.len(), 1);
+    let opportunity = &opportunities[0];
+    assert_eq!(opportunity.market_a, MARKET);
+    assert_eq!(opportunity.strategy, "buy_yes_set");
+    assert_eq!(opportunity.outcome_count, 3);
+    assert_eq!(common::decimal(&opportunity.yes_price_sum), common::decimal("0.9"));
+    assert_eq!(common::decimal(&opportunity.potential_profit), common::decimal("0.1"));
+    assert_eq!(opportunity.outcomes[1].yes_price, "0.45");
+}
+
+#[test]
+fn partially_priced_or_fair_markets_are_skipped() {
+    let stores = registered_market();
+    assert!(stores
+        .arbitrage(vec![
+            common::buy_fill("neg_risk", &common::yes(CONDITIONS[0]), 300, 1000, 1),
+            common::buy_fill("neg_risk", &common::yes(CONDITIONS[1]), 600, 1000, 2),
+        ])
+        .is_empty());
+
+    // 0.30 + 0.60 + 0.11 = 1.01 is within the default 0.02
+    assert!(stores.arbitrage(vec![common::buy_fill("neg_risk", &common::yes(CONDITIONS[2]), 110, 1000, 3)]).is_empty());
+}
+
+#[test]
+fn position_ids_match_polymarket() {
+    // "Will Donald Trump win the 2024 US Presidential Election?", a Neg Risk market
+    let condition = hex::decode("dd22472e552920b8438158ea7238bfadfa4f736aa4cee91a6b86c39ead110917").unwrap();
+    let collateral = hex::decode(registry::POLYGON_NEG_RISK_WRAPPED_COLLATERAL).unwrap();
+
+    assert_eq!(
+        neg_risk::position_id(&collateral, &condition, 1),
+        "21742633143463906290569050155826241533067272736897614950488156847949938836455"
+    );
+    assert_eq!(
+        neg_risk::position_id(&collateral, &condition, 2),
+        "48331043336612883890938759509493159234755048973500640148014422747788308965732"
+    );
+}
+
+#[test]
+fn v2_market_is_known_from_question_prepared() {
+    let registry = ContractRegistry::default();
+    let stores = common::Outcomes::default();
+
+    // A v2-only market: its questions are prepared in the block, with no TokenRegistered
+    let mut blk = common::load_block("v2_neg_risk");
+    common::prepare_questions(&mut blk.transaction_traces[0], MARKET, 3);
+    common::replace_token_id(&mut blk, common::V2_NEG_RISK_TOKEN, &common::yes(CONDITIONS[1]));
+
+    let events = extract_exchange_events(&registry, &blk);
+    let questions = stores.prepare(&blk);
+    update_registered_tokens(&registry, events.clone(), questions, &stores.tokens);
+    assert!(events.admin_events.iter().all(|e| e.event_type != "TokenRegistered"));
+
+    let registered = stores.tokens.get(&format!("token:{}", common::no(CONDITIONS[1]))).unwrap();
+    assert_eq!((registered.outcome.as_str(), registered.complement_token_id.clone()), ("no", common::yes(CONDITIONS[1])));
+    assert_eq!((registered.exchange.as_str(), registered.exchange_version.as_str()), ("neg_risk", "v2"));
+
+    let fills = combine_order_fills(common::clock(&blk), events);
+    assert!(!fills.events.is_empty());
+    stores.arbitrage(fills.events);
+
+    let price = stores.outcome_prices.get(&format!("price:{}:1", MARKET)).unwrap();
+    assert_eq!((price.token_id.clone(), price.yes_price.as_str()), (common::yes(CONDITIONS[1]), "0.25"));
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use substreams::pb::substreams::store_delta::Operation;
+
+const T1: &str = "21742633143463906290569050155826241533067272736897614950488156847949938836455";
+const T2: &str = "48331043336612883890938759509493159234755048973500640148014422747788308965733";
+const T3: &str = "71321045679252212594626385532706912750332728571942532289631379312455583992563";
+const T4: &str = "52114319501245915516055106046884209969926127482827954674443846427813813222426";
+
+const ALICE: &str = "9d84ce0306f8551e02efef1680475fc0f1dc1344";
+const BOB: &str = "d42f6a1634a3707e27cbae14ca966068e5d1047d";
+const CAROL: &str = "3cf3e8d5427aed066a7a5926980600f6c3cf87b3";
+
+/// Fixture blocks in chain order, spanning the v1 → v2 cutover
+const BLOCKS: [&str; 5] = ["v1_ctf", "v1_neg_risk", "cutover", "v2_ctf", "v2_neg_risk"];
+
+fn market_key(token_id: &str) -> String {
+    format!("market:condition_{}", token_id)
+}
+
+#[test]
+fn stores_accumulate_across_the_cutover() {
+    let replay = common::Replay::new();
+    for name in BLOCKS {
+        replay.process(&common::load_block(name));
+    }
+
+    let mut expected_markets: Vec<String> = [T1, T2, T3, T4].iter().map(|t| market_key(t)).collect();
+    expected_markets.sort();
+    assert_eq!(replay.markets.keys(), expected_markets);
+
+    let mut expected_traders: Vec<String> = [
+        ALICE,
+        BOB,
+        CAROL,
+        "4bfb41d5b3570defd03c39a9a4d8de6bd8b8982e",
+        "c5d563a36ae78145c45a50134d48a1215220f80a",
+        "e111180000d2663c0091e4f400237545b87b996b",
+        "e2222d279d744050d28e00520010520000310f59",
+    ]
+    .iter()
+    .map(|a| format!("trader:{}", a))
+    .collect();
+    expected_traders.sort();
+    assert_eq!(replay.traders.keys(), expected_traders);
+
+    // T1 traded on v1 before the cutover and again in the cutover block
+    let t1 = replay.markets.get(&market_key(T1)).unwrap();
+    assert_eq!(t1.last_updated_block, 86_112_000);
+    assert_eq!(t1.collateral_volume, "6000000");
+    assert_eq!(t1.mid_price, "0.5");
+
+    // T4 was bought in the cutover block and sold on v2 Neg Risk afterwards
+    let t4 = replay.markets.get(&market_key(T4)).unwrap();
+    assert_eq!(t4.last_updated_block, 86_200_050);
+    assert_eq!((t4.buys_quantity, t4.sells_quantity), (0, 1));
+
+    let alice = replay.traders.get(&format!("trader:{}", ALICE)).unwrap();
+    assert_eq!(alice.trader_type, "maker");
+    assert_eq!(alice.total_volume, "8000000");
+
+    let global = replay.global.get("global").unwrap();
+    assert_eq!(global.trades_quantity, 2);
+    assert_eq!(global.collateral_volume, "5000000");
+}
+
+#[test]
+fn deltas_reach_layer_3_per_block() {
+    let replay = common::Replay::new();
+
+    let first = replay.process(&common::load_block("v1_ctf"));
+    // Both fills trade the same token: one orderbook, not one per fill
+    assert_eq!(first.market_orderbooks.orderbooks.len(), 1);
+    assert_eq!(first.market_orderbooks.block_number, 65_000_000);
+    assert_eq!(first.trader_accounts.accounts.len(), 4);
+    assert_eq!(first.global_stats.trades_quantity, 2);
+    assert_eq!((first.global_stats.buys_quantity, first.global_stats.sells_quantity), (1, 1));
+    assert_eq!(first.global_stats.collateral_volume, "15000000");
+    assert_eq!(first.global_stats.total_fees, "100000");
+
+    replay.process(&common::load_block("v1_neg_risk"));
+
+    let cutover = replay.process(&common::load_block("cutover"));
+    let ids: Vec<&str> = cutover.market_orderbooks.orderbooks.iter().map(|o| o.id.as_str()).collect();
+    let t1 = format!("condition_{}", T1);
+    let t4 = format!("condition_{}", T4);
+    assert_eq!(ids, vec![t1.as_str(), t4.as_str()]);
+    assert_eq!(cutover.global_stats.trades_quantity, 4);
+    assert_eq!((cutover.global_stats.buys_quantity, cutover.global_stats.sells_quantity), (2, 2));
+    assert_eq!(cutover.global_stats.collateral_volume, "21500000");
+}
+
+#[test]
+fn store_operations_track_existing_keys() {
+    let replay = common::Replay::new();
+    replay.process(&common::load_block("v1_ctf"));
+    replay.process(&common::load_block("v1_neg_risk"));
+
+    // Drive the store directly to inspect deltas before Layer 3 consumes them
+    let fills = polymarket_orderbook_substreams::combine_order_fills(
+        common::clock(&common::load_block("cutover")),
+        polymarket_orderbook_substreams::extract_exchange_events(&replay.registry, &common::load_block("cutover")),
+    );
+    polymarket_orderbook_substreams::update_markets(fills, &replay.markets);
+    let deltas = replay.markets.end_block().deltas;
+
+    assert_eq!(deltas.len(), 4);
+    assert_eq!(deltas[0].key, market_key(T1));
+    assert_eq!(deltas[0].operation, Operation::Update);
+    assert_eq!(deltas[0].old_value.last_updated_block, 65_000_000);
+    assert_eq!(deltas[1].operation, Operation::Update);
+    assert_eq!(deltas[1].old_value.last_updated_block, 86_112_000);
+    assert_eq!(deltas[2].key, market_key(T4));
+    assert_eq!(deltas[2].operation, Operation::Create);
+    assert_eq!(deltas[3].operation, Operation::Update);
+}
+
+#[test]
+fn empty_block_leaves_stores_untouched() {
+    let replay = common::Replay::new();
+    replay.process(&common::load_block("v2_ctf"));
+
+    let mut empty = common::load_block("v2_ctf");
+    empty.number += 1;
+    empty.transaction_traces.clear();
+    let outputs = replay.process(&empty);
+
+    assert!(outputs.fills.events.is_empty());
+    assert!(outputs.market_orderbooks.orderbooks.is_empty());
+    assert!(outputs.trader_accounts.accounts.is_empty());
+    assert_eq!(outputs.global_stats.trades_quantity, 0);
+    assert_eq!(replay.global.get("global").unwrap().collateral_volume, "13000000");
+}
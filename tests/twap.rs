@@ -0,0 +1,85 @@
+mod common;
+
+use polymarket_orderbook_substreams::pb::polymarket::orderbook::v1::{OrderFilledEvent, TokenPrice, TwapPrice};
+use polymarket_orderbook_substreams::twap;
+use polymarket_orderbook_substreams::{twap_prices, update_last_price, update_twap_accumulators};
+use substreams::pb::substreams::Clock;
+use substreams::scalar::BigInt;
+
+/// Start of a 5-minute slot of the 1h window
+const T0: i64 = 1_700_000_400;
+
+#[derive(Default)]
+struct Stores {
+    last_price: common::store::MemoryStore<TokenPrice>,
+    accumulators: common::store::MemoryStore<BigInt>,
+}
+
+/// Runs one block through store_last_price, store_twap_accumulators and map_twap_prices
+fn block(stores: &Stores, seconds: i64, fills: Vec<OrderFilledEvent>) -> Vec<TwapPrice> {
+    let fills = common::block_fills(seconds, fills);
+    let clock = Clock {
+        timestamp: common::timestamp(seconds),
+        ..Default::default()
+    };
+    let windows = twap::windows_from_params("window:3600").unwrap();
+
+    update_last_price(fills.clone(), &stores.last_price);
+    update_twap_accumulators(&windows, clock, stores.last_price.end_block(), &stores.accumulators);
+    stores.accumulators.end_block();
+
+    twap_prices(&windows, fills, |key| stores.last_price.get(key), |key| stores.accumulators.get(key)).prices
+}
+
+#[test]
+fn last_price_follows_the_latest_fill() {
+    let stores = Stores::default();
+    block(&stores, T0, vec![common::fill_at("1", 400, T0, 1), common::fill_at("1", 450, T0, 2)]);
+
+    let last = stores.last_price.get("1").unwrap();
+    assert_eq!((common::decimal(&last.price), last.ordinal), (common::decimal("0.45"), 2));
+}
+
+#[test]
+fn twap_weights_prices_by_time_held() {
+    let stores = Stores::default();
+
+    // A price held for no time yet has no TWAP
+    assert!(block(&stores, T0, vec![common::fill_at("1", 400, T0, 1)]).is_empty());
+
+    let prices = block(&stores, T0 + 600, vec![common::fill_at("1", 600, T0 + 600, 1)]);
+    assert_eq!(common::decimal(&prices[0].twap), common::decimal("0.4"));
+    assert_eq!(prices[0].covered_seconds, 600);
+
+    // 0.4 for 600s then 0.6 for 1800s
+    let prices = block(&stores, T0 + 2400, vec![common::fill_at("1", 500, T0 + 2400, 1)]);
+    assert_eq!(common::decimal(&prices[0].twap), common::decimal("0.55"));
+    assert_eq!((prices[0].covered_seconds, prices[0].window_seconds), (2400, 3600));
+    assert_eq!(common::decimal(&prices[0].last_price), common::decimal("0.5"));
+}
+
+#[test]
+fn time_outside_the_window_is_dropped() {
+    let stores = Stores::default();
+    block(&stores, T0, vec![common::fill_at("1", 400, T0, 1)]);
+
+    // 0.4 held for two hours, then 0.8 for ten minutes. The window reads the 11 slots before the
+    // current one, which has just started: 45 minutes of 0.4 and 10 of 0.8.
+    block(&stores, T0 + 7200, vec![common::fill_at("1", 800, T0 + 7200, 1)]);
+    let prices = block(&stores, T0 + 7800, vec![common::fill_at("1", 800, T0 + 7800, 1)]);
+    assert_eq!(prices[0].covered_seconds, 3300);
+    let expected = (common::decimal("0.4") * common::decimal("2700") + common::decimal("0.8") * common::decimal("600")) / common::decimal("3300");
+    assert_eq!(common::decimal(&prices[0].twap), expected.round(twap::PRICE_DECIMALS as i64));
+
+    // The slot that just left the window is pruned
+    let expired = twap::slot_of(3600, (T0 + 7800) as u64) - twap::SLOTS_PER_WINDOW;
+    assert!(stores.accumulators.keys().iter().all(|k| !k.starts_with(&twap::slot_prefix(3600, expired))));
+}
+
+#[test]
+fn invalid_windows_are_rejected() {
+    assert_eq!(twap::windows_from_params("").unwrap(), vec![3600, 86400]);
+    assert!(twap::windows_from_params("window:30").is_err());
+    assert!(twap::windows_from_params("window:3601").is_err());
+    assert!(twap::windows_from_params("hours:1").is_err());
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use std::cell::Cell;
+
+use polymarket_orderbook_substreams::pb::polymarket::orderbook::v1::{
+    MarketOrderbook, OrderFilledEvent, TokenPrice, TokenVolatility,
+};
+use polymarket_orderbook_substreams::{
+    market_orderbooks, token_volatility, twap, update_last_price, update_markets, update_volatility_accumulators, volatility,
+};
+use substreams::pb::substreams::Clock;
+use substreams::scalar::BigInt;
+
+const T0: i64 = 1_700_000_400;
+
+#[derive(Default)]
+struct Stores {
+    last_price: common::store::MemoryStore<TokenPrice>,
+    accumulators: common::store::MemoryStore<BigInt>,
+}
+
+/// Runs one block through store_last_price, store_volatility_accumulators and map_token_volatility
+fn block(stores: &Stores, seconds: i64, fills: Vec<OrderFilledEvent>) -> Vec<TokenVolatility> {
+    let fills = common::block_fills(seconds, fills);
+    let clock = Clock {
+        timestamp: common::timestamp(seconds),
+        ..Default::default()
+    };
+    let windows = twap::windows_from_params("window:3600").unwrap();
+
+    update_last_price(fills.clone(), &stores.last_price);
+    update_volatility_accumulators(&windows, clock, stores.last_price.end_block(), &stores.accumulators);
+    stores.accumulators.end_block();
+
+    token_volatility(&windows, fills, |key| stores.accumulators.get(key)).volatilities
+}
+
+fn approx(value: &str, expected: f64) -> bool {
+    (value.parse::<f64>().unwrap() - expected).abs() < 1e-9
+}
+
+#[test]
+fn returns_between_fills_in_one_block() {
+    let stores = Stores::default();
+
+    // 0.5 → 0.6 → 0.5: two log-odds returns of ±ln(1.5)
+    let volatilities = block(&stores, T0, vec![common::fill_at("1", 500, T0, 1), common::fill_at("1", 600, T0, 2), common::fill_at("1", 500, T0, 3)]);
+    assert_eq!(volatilities[0].returns, 2);
+    assert!(approx(&volatilities[0].volatility, (2.0 * 1.5f64.ln().powi(2)).sqrt()), "{:?}", volatilities[0]);
+}
+
+#[test]
+fn first_fill_has_no_return() {
+    let stores = Stores::default();
+    assert!(block(&stores, T0, vec![common::fill_at("1", 500, T0, 1)]).is_empty());
+
+    let volatilities = block(&stores, T0 + 60, vec![common::fill_at("1", 500, T0 + 60, 1)]);
+    assert_eq!((volatilities[0].returns, volatilities[0].window_seconds), (1, 3600));
+    assert!(approx(&volatilities[0].volatility, 0.0));
+}
+
+#[test]
+fn returns_leave_the_window() {
+    let stores = Stores::default();
+    block(&stores, T0, vec![common::fill_at("1", 500, T0, 1), common::fill_at("1", 600, T0, 2)]);
+
+    let volatilities = block(&stores, T0 + 7200, vec![common::fill_at("1", 600, T0 + 7200, 1)]);
+    assert_eq!(volatilities[0].returns, 1);
+    assert!(approx(&volatilities[0].volatility, 0.0));
+}
+
+#[test]
+fn log_odds_are_clamped() {
+    assert_eq!(volatility::log_odds("0"), volatility::log_odds("0.001"));
+    assert!((volatility::log_odds("1") + volatility::log_odds("0")).abs() < 1e-12);
+    assert_eq!(volatility::log_odds("0.5"), 0.0);
+}
+
+#[test]
+fn orderbooks_read_each_token_volatility_once() {
+    let stores = Stores::default();
+    block(&stores, T0, vec![common::fill_at("1", 400, T0, 1)]);
+    let fills = vec![common::fill_at("1", 500, T0 + 60, 1), common::fill_at("1", 450, T0 + 60, 2), common::fill_at("1", 480, T0 + 60, 3)];
+    block(&stores, T0 + 60, fills.clone());
+
+    let markets = common::store::MemoryStore::<MarketOrderbook>::new();
+    let fills = common::block_fills(T0 + 60, fills);
+    update_markets(fills.clone(), &markets);
+
+    let reads = Cell::new(0);
+    let orderbooks = market_orderbooks(fills, markets.end_block(), |_| None, 3600, |key| {
+        reads.set(reads.get() + 1);
+        stores.accumulators.get(key)
+    }, |_| None)
+    .orderbooks;
+
+    let token_reads = Cell::new(0);
+    let realized = volatility::realized_volatility(3600, (T0 + 60) as u64, "1", |key| {
+        token_reads.set(token_reads.get() + 1);
+        stores.accumulators.get(key)
+    });
+    assert_eq!(orderbooks.len(), 1);
+    assert!(realized.is_some());
+    assert_eq!(reads.get(), token_reads.get());
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use polymarket_orderbook_substreams::pb::polymarket::orderbook::v1::{OrderFilledEvent, WashTradeSignal};
+use polymarket_orderbook_substreams::wash::WashParams;
+use polymarket_orderbook_substreams::{update_counterparty_pairs, update_wash_adjusted_volume, wash_trade_signals};
+use substreams::scalar::BigInt;
+
+const T0: i64 = 1_700_002_800;
+const ALICE: &str = "d42f6a1634a3707e27cbae14ca966068e5d1047d";
+const BOB: &str = "9d84ce0306f8551e02efef1680475fc0f1dc1344";
+
+#[derive(Default)]
+struct Stores {
+    pairs: common::store::MemoryStore<BigInt>,
+    volume: common::store::MemoryStore<BigInt>,
+}
+
+/// Runs one block through store_counterparty_pairs, map_wash_trade_signals and store_wash_adjusted_volume
+fn block(stores: &Stores, seconds: i64, fills: Vec<OrderFilledEvent>) -> Vec<WashTradeSignal> {
+    let fills = common::block_fills(seconds, fills);
+    let params = WashParams::default();
+
+    update_counterparty_pairs(fills.clone(), &stores.pairs);
+    stores.pairs.end_block();
+    let signals = wash_trade_signals(&params, fills.clone(), |key| stores.pairs.get(key));
+    update_wash_adjusted_volume(fills, signals.clone(), &stores.volume);
+    stores.volume.end_block();
+
+    signals.signals
+}
+
+fn wash_adjusted_volume(stores: &Stores) -> String {
+    stores.volume.get("7").unwrap().to_string()
+}
+
+#[test]
+fn self_trades_are_flagged() {
+    let stores = Stores::default();
+    let signals = block(&stores, T0, vec![common::fill_between(ALICE, ALICE, 1)]);
+
+    assert_eq!(signals.len(), 1);
+    assert_eq!((signals[0].kind.as_str(), signals[0].buyer.as_str()), ("self_trade", ALICE));
+    assert_eq!(signals[0].collateral_amount, "500");
+    assert!(stores.pairs.keys().is_empty());
+    assert_eq!(wash_adjusted_volume(&stores), "0");
+}
+
+#[test]
+fn trading_back_and_forth_is_circular() {
+    let stores = Stores::default();
+
+    // One way only, however often, is not circular
+    assert!(block(&stores, T0, vec![common::fill_between(ALICE, BOB, 1), common::fill_between(ALICE, BOB, 2)]).is_empty());
+    assert_eq!(wash_adjusted_volume(&stores), "1000");
+    assert!(block(&stores, T0 + 60, vec![common::fill_between(BOB, ALICE, 1)]).is_empty());
+
+    let signals = block(&stores, T0 + 120, vec![common::fill_between(BOB, ALICE, 1)]);
+    assert_eq!(signals.len(), 1);
+    assert_eq!(signals[0].kind, "circular");
+    assert_eq!((signals[0].buyer.as_str(), signals[0].seller.as_str()), (BOB, ALICE));
+    assert_eq!((signals[0].forward_fills, signals[0].reverse_fills), (2, 2));
+    // Only the flagged fill is left out of the running volume
+    assert_eq!(wash_adjusted_volume(&stores), "1500");
+}
+
+#[test]
+fn fills_against_the_exchange_have_no_counterparty() {
+    let stores = Stores::default();
+    // Matched orders fill the taker order against the exchange contract that emitted the log
+    let fills = vec![common::fill_between(ALICE, common::EXCHANGE, 1), common::fill_between(BOB, common::EXCHANGE, 2)];
+
+    assert!(block(&stores, T0, fills.clone()).is_empty());
+    assert!(block(&stores, T0 + 60, fills).is_empty());
+    assert!(stores.pairs.keys().is_empty());
+}
+
+#[test]
+fn taker_order_fills_are_attributed_to_the_pair() {
+    let stores = Stores::default();
+    block(&stores, T0, vec![common::fill_between(ALICE, BOB, 1), common::fill_between(ALICE, BOB, 2), common::fill_between(BOB, ALICE, 3)]);
+
+    // ALICE's taker order matches BOB's maker order, then is filled against the exchange
+    let signals = block(&stores, T0 + 60, vec![common::fill_between(BOB, ALICE, 1), common::fill_between(ALICE, common::EXCHANGE, 2)]);
+
+    assert_eq!(signals.len(), 2);
+    assert_eq!(signals[1].fill_id, "0xhash-2");
+    assert_eq!((signals[1].buyer.as_str(), signals[1].seller.as_str()), (BOB, ALICE));
+    assert_eq!(signals[1].collateral_amount, "500");
+    // The three unflagged fills of the first block are all that is left
+    assert_eq!(wash_adjusted_volume(&stores), "1500");
+}
+
+#[test]
+fn pairs_leave_the_window() {
+    let stores = Stores::default();
+    block(&stores, T0, vec![common::fill_between(ALICE, BOB, 1), common::fill_between(ALICE, BOB, 2), common::fill_between(BOB, ALICE, 3)]);
+
+    // Two hours later the earlier fills no longer count
+    assert!(block(&stores, T0 + 7200, vec![common::fill_between(BOB, ALICE, 1)]).is_empty());
+    assert!(stores.pairs.keys().iter().all(|k| k.starts_with(&format!("{}:", (T0 as u64 + 7200) / 3600))));
+}
+
+#[test]
+fn params() {
+    assert_eq!(WashParams::parse("").unwrap().min_round_trips, 2);
+    assert_eq!(WashParams::parse("min_round_trips:1").unwrap().min_round_trips, 1);
+    assert!(WashParams::parse("min_round_trips:0").is_err());
+    assert!(WashParams::parse("round_trips:2").is_err());
+}